@@ -2,11 +2,13 @@ use anyhow::{anyhow, Context, Result};
 
 use askalono::{ScanMode, ScanStrategy, Store, TextData};
 
+use async_trait::async_trait;
+
 use bzip2::bufread::BzDecoder;
+use clap::ValueEnum;
 use colored::Colorize;
 use flate2::bufread::GzDecoder;
 use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
-use goblin::Object;
 use headway::ProgressBarIterable;
 use infer::Infer;
 use lazy_static::lazy_static;
@@ -15,13 +17,15 @@ use reqwest::{
     redirect::Policy,
     Method, RequestBuilder, Url,
 };
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
     borrow::Borrow,
-    collections::{BTreeMap, BTreeSet, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     env,
-    io::{ErrorKind, Read},
+    io::{BufRead, ErrorKind, Read, Seek},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tar::Archive;
 use tempdir::TempDir;
@@ -31,13 +35,21 @@ use tokio::{
 };
 use tracing::{debug, error, info, trace, warn};
 use xz2::bufread::XzDecoder;
+use zip::ZipArchive;
 
 use crate::{
-    PackageArtifact, PackageIdent, PackageMetadata, PackageType, ValidFilePath, HAB_CACHE_SRC_PATH,
-    HAB_PKGS_PATH,
+    conflicts::find_file_conflicts,
+    elf::{package_ident, ElfObject, LibraryResolver, ResolvedLibrary},
+    regression::{self, ArtifactSummary, RegressionThresholds},
+    resolve_in_root,
+    spdx::{is_license_ref, LicenseExpression},
+    ArtifactEntry, ArtifactEntryType, PackageArtifact, PackageArtifactContents,
+    PackageArtifactIdent, PackageIdent, PackageMetadata, PackageTarget, PackageType, ValidFilePath,
+    HAB_CACHE_SRC_PATH, HAB_PKGS_PATH,
 };
 
 lazy_static! {
+    /// Host interpreters that are available on every platform, used unless configured
     static ref PLATFORM_SHELLS: Vec<PathBuf> = vec![
         PathBuf::from(option_env!("HAB_PLATFORM_SHELL").unwrap_or("/bin/sh")),
         PathBuf::from("/bin/false")
@@ -45,10 +57,28 @@ lazy_static! {
     static ref MANIFEST_METAFILE: PathBuf = PathBuf::from("MANIFEST");
 }
 
+/// How many levels of archives within the package source archive are scanned for licenses
+const MAX_NESTED_ARCHIVE_DEPTH: usize = 1;
+
+/// Largest nested archive that is read into memory to be scanned for licenses
+const MAX_NESTED_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
 const LICENSE_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/license-cache.bin.gz"));
 const DEPRECATED_LICENSE_DATA: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/deprecated-license-cache.bin.gz"));
 
+/// The license and exception identifiers of the bundled SPDX license list, including deprecated ones
+pub fn license_list_ids() -> HashSet<String> {
+    let mut ids = HashSet::new();
+    for data in [LICENSE_DATA, DEPRECATED_LICENSE_DATA] {
+        match Store::from_cache(data) {
+            Ok(store) => ids.extend(store.licenses().cloned()),
+            Err(err) => error!("Failed to load the SPDX license list: {:#}", err),
+        }
+    }
+    ids
+}
+
 const LICENSE_GLOBS: &[&str] = &[
     // General
     "COPYING",
@@ -89,19 +119,234 @@ const LICENSE_GLOBS: &[&str] = &[
 pub struct LicenseCheck {
     file_type_checker: Infer,
     fs_root: PathBuf,
+    sources: SourceConfiguration,
+    evidence: Vec<LicenseEvidence>,
+    declared_licenses: Option<LicenseExpression>,
+    license_files: Vec<PathBuf>,
     license_globs: GlobSet,
     license_store: Store,
     deprecated_license_store: Store,
 }
 
-impl LicenseCheck {
-    fn id(&self) -> &'static str {
-        "LICENSE_CHECK"
+/// Source and license information from a package MANIFEST metafile
+#[derive(Debug, Default)]
+pub struct PackageManifest {
+    pub source: Option<Url>,
+    pub shasum: Option<String>,
+    pub licenses: Option<String>,
+}
+
+impl PackageManifest {
+    pub fn parse(data: &str) -> Result<PackageManifest> {
+        let mut manifest = PackageManifest::default();
+        for line in data.lines() {
+            if let Some(src) = line.strip_prefix("* __Source__:") {
+                let src = src.trim().split_terminator(&['[', ']']).collect::<Vec<_>>();
+                if let Some(url) = src.get(1) {
+                    manifest.source = Some(Url::parse(url)?);
+                }
+            }
+            if let Some(shasum) = line.strip_prefix("* __SHA__:") {
+                let patterns: &[_] = &[' ', '`', '\n'];
+                manifest.shasum = Some(shasum.trim_matches(patterns).to_owned());
+            }
+            if let Some(licenses) = line.strip_prefix("* __License__:") {
+                manifest.licenses = Some(licenses.trim().to_owned());
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// The scan strategy with which a license was found, in decreasing order of confidence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseScanStrategy {
+    Normal,
+    Deep,
+    Suspect,
+}
+
+/// Compression of a tar archive
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+    Bzip2,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+/// Archive formats the license scanner can look into
+#[derive(Debug, Clone, Copy)]
+enum ArchiveFormat {
+    Tar(Option<Compression>),
+    /// Zip archives, including java archives and python wheels
+    Zip,
+}
+
+impl ArchiveFormat {
+    fn from_path(path: &Path) -> Option<ArchiveFormat> {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("bz2" | "tb2" | "tbz" | "tbz2" | "tz2") => {
+                Some(ArchiveFormat::Tar(Some(Compression::Bzip2)))
+            }
+            Some("gz" | "taz" | "tgz") => Some(ArchiveFormat::Tar(Some(Compression::Gzip))),
+            Some("lzma" | "tlz" | "xz" | "txz") => Some(ArchiveFormat::Tar(Some(Compression::Xz))),
+            Some("zst" | "tzst") => Some(ArchiveFormat::Tar(Some(Compression::Zstd))),
+            Some("tar") => Some(ArchiveFormat::Tar(None)),
+            Some("zip" | "jar" | "war" | "whl") => Some(ArchiveFormat::Zip),
+            _ => None,
+        }
+    }
+
+    fn from_mime_type(mime_type: &str) -> Option<ArchiveFormat> {
+        match mime_type {
+            "application/x-bzip2" => Some(ArchiveFormat::Tar(Some(Compression::Bzip2))),
+            "application/gzip" => Some(ArchiveFormat::Tar(Some(Compression::Gzip))),
+            "application/x-xz" => Some(ArchiveFormat::Tar(Some(Compression::Xz))),
+            "application/zstd" => Some(ArchiveFormat::Tar(Some(Compression::Zstd))),
+            "application/x-tar" => Some(ArchiveFormat::Tar(None)),
+            "application/zip" => Some(ArchiveFormat::Zip),
+            _ => None,
+        }
     }
-    fn description(&self) -> &'static str {
-        "Checks package licenses"
+}
+
+/// Where a license file was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseLocation {
+    /// In the package source archive
+    Source,
+    /// In the installed package contents
+    Package,
+}
+
+/// A license found in a file of the package source archive or the package itself
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseEvidence {
+    pub license: String,
+    pub path: PathBuf,
+    pub score: f32,
+    pub strategy: LicenseScanStrategy,
+    pub location: LicenseLocation,
+}
+
+impl std::fmt::Display for LicenseEvidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {}{} (score {:.2}, {} scan)",
+            self.license,
+            match self.location {
+                LicenseLocation::Source => "",
+                LicenseLocation::Package => "installed file ",
+            },
+            self.path.display(),
+            self.score,
+            match self.strategy {
+                LicenseScanStrategy::Normal => "normal",
+                LicenseScanStrategy::Deep => "deep",
+                LicenseScanStrategy::Suspect => "suspect",
+            }
+        )
+    }
+}
+
+/// Licenses found in a package source archive
+#[derive(Debug, Default)]
+pub struct LicenseScan {
+    pub evidence: Vec<LicenseEvidence>,
+}
+
+impl LicenseScan {
+    /// Licenses found by the normal or deep scan
+    pub fn detected(&self) -> BTreeSet<String> {
+        self.evidence
+            .iter()
+            .filter(|evidence| evidence.strategy != LicenseScanStrategy::Suspect)
+            .map(|evidence| evidence.license.clone())
+            .collect()
+    }
+
+    /// Licenses found only by the low confidence suspect scan
+    pub fn suspected(&self) -> BTreeSet<String> {
+        self.evidence
+            .iter()
+            .filter(|evidence| evidence.strategy == LicenseScanStrategy::Suspect)
+            .map(|evidence| evidence.license.clone())
+            .collect()
+    }
+
+    /// Describes where the given licenses were found
+    pub fn describe<'a>(&self, licenses: impl IntoIterator<Item = &'a String>) -> String {
+        let licenses = licenses.into_iter().collect::<BTreeSet<_>>();
+        self.evidence
+            .iter()
+            .filter(|evidence| licenses.contains(&evidence.license))
+            .map(|evidence| evidence.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Where package source archives are fetched from when they are not in the source cache
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SourceConfiguration {
+    /// Directory containing source archives named as in the cache, checked before downloading
+    pub mirror_dir: Option<PathBuf>,
+    /// Rewrites applied to the source url before downloading, the first matching rule is used
+    #[serde(default)]
+    pub url_rewrites: Vec<UrlRewrite>,
+    /// User agent sent when downloading sources
+    pub user_agent: Option<String>,
+    /// Never download sources
+    #[serde(default)]
+    pub offline: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UrlRewrite {
+    pub prefix: String,
+    pub replacement: String,
+}
+
+impl SourceConfiguration {
+    fn rewrite_url(&self, url: &Url) -> Result<Url> {
+        for rewrite in self.url_rewrites.iter() {
+            if let Some(rest) = url.as_str().strip_prefix(rewrite.prefix.as_str()) {
+                let rewritten_url = Url::parse(&format!("{}{}", rewrite.replacement, rest))
+                    .with_context(|| {
+                        format!(
+                            "Invalid url after rewriting '{}' with '{}'",
+                            url, rewrite.replacement
+                        )
+                    })?;
+                debug!("Rewrote source url {} to {}", url, rewritten_url);
+                return Ok(rewritten_url);
+            }
+        }
+        Ok(url.clone())
     }
-    pub fn new(fs_root: impl AsRef<Path>) -> Result<LicenseCheck> {
+}
+
+impl LicenseCheck {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "LICENSE_CHECK",
+        description: "Checks package licenses",
+        default_severity: CheckSeverity::Error,
+        rules: &[
+            "SOURCE_UNAVAILABLE",
+            "SOURCE_LICENSES",
+            "UNDECLARED_LICENSE",
+            "LICENSE_FILES",
+            "INVALID_EXPRESSION",
+            "DEPRECATED_LICENSE",
+            "UNKNOWN_LICENSE",
+        ],
+    };
+
+    pub fn new(fs_root: impl AsRef<Path>, sources: SourceConfiguration) -> Result<LicenseCheck> {
         let license_store = Store::from_cache(LICENSE_DATA)?;
         let deprecated_license_store = Store::from_cache(DEPRECATED_LICENSE_DATA)?;
         debug!("{} licenses loaded", license_store.licenses().count());
@@ -127,148 +372,287 @@ impl LicenseCheck {
             deprecated_license_store,
             license_globs,
             fs_root: fs_root.as_ref().to_path_buf(),
+            sources,
+            evidence: Vec::new(),
+            declared_licenses: None,
+            license_files: Vec::new(),
         })
     }
-    async fn visit_dir_start(
-        &mut self,
-        _abs_path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
+}
+
+#[async_trait]
+impl ArtifactCheck for LicenseCheck {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &LicenseCheck::METADATA
+    }
+    async fn visit_dir_start(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<DirReport> {
         Ok(DirReport::default())
     }
-    async fn visit_dir_end(
-        &mut self,
-        _path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
+    async fn visit_dir_end(&mut self, _path: &Path, rel_path: &Path) -> Result<DirReport> {
         Ok(DirReport::default())
     }
-    async fn visit_child_dir(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
+    async fn visit_child_dir(&mut self, _path: &Path, _rel_path: &Path) -> Result<DirReport> {
         Ok(DirReport::default())
     }
-    async fn visit_file(
-        &mut self,
-        path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
-        if rel_path.as_ref() == MANIFEST_METAFILE.as_path() {
-            let file = tokio::fs::File::open(path).await?;
-            let mut reader = tokio::io::BufReader::new(file);
-            let mut pkg_source = None;
-            let mut pkg_shasum = None;
-            let mut pkg_licenses = None;
-            loop {
-                let mut line = String::new();
-                match reader.read_line(&mut line).await {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        if let Some(src) = line.strip_prefix("* __Source__:") {
-                            let src = src.trim().split_terminator(&['[', ']']).collect::<Vec<_>>();
-                            if let Some(url) = src.get(1) {
-                                pkg_source = Some(Url::parse(url)?);
-                            }
-                        }
-                        if let Some(shasum) = line.strip_prefix("* __SHA__:") {
-                            let patterns: &[_] = &[' ', '`', '\n'];
-                            pkg_shasum = Some(shasum.trim_matches(patterns).to_owned());
-                        }
-                        if let Some(licenses) = line.strip_prefix("* __License__:") {
-                            pkg_licenses = Some(
-                                licenses
-                                    .trim()
-                                    .split(' ')
-                                    .map(String::from)
-                                    .collect::<Vec<String>>(),
-                            );
-                        }
-                    }
-                    Err(err) => {
-                        error!("Failed to read package MANIFEST file");
-                        break;
-                    }
-                }
-            }
+    async fn visit_file(&mut self, path: &Path, rel_path: &Path) -> Result<FileReport> {
+        if rel_path == MANIFEST_METAFILE.as_path() {
+            let manifest =
+                PackageManifest::parse(&String::from_utf8_lossy(&tokio::fs::read(path).await?))?;
+            let (pkg_source, pkg_shasum, pkg_licenses) =
+                (manifest.source, manifest.shasum, manifest.licenses);
+            let mut report = FileReport::default();
+            let pkg_licenses = pkg_licenses
+                .filter(|licenses| !licenses.is_empty())
+                .map(|licenses| self.parse_license_expression(&licenses, &mut report));
+            self.declared_licenses = pkg_licenses.clone();
             if let (Some(url), Some(sha)) = (pkg_source, pkg_shasum) {
-                let mut report = FileReport::default();
-                let (detected_licenses, suspected_licenses) =
-                    self.check(self.fs_root.as_path(), url, &sha).await?;
-                if !detected_licenses.is_empty() {
-                    if let Some(pkg_licenses) = pkg_licenses {
-                        for pkg_license in pkg_licenses.iter() {
-                            if !detected_licenses.contains(pkg_license) {
-                                report.warnings.push(format!("Package has license '{}' which was not found in the source, detected licenses: {:?}", pkg_license, detected_licenses))
-                            }
-                        }
-                        let pkg_licenses = pkg_licenses.iter().cloned().collect::<BTreeSet<_>>();
-                        if report.warnings.is_empty() && detected_licenses != pkg_licenses {
-                            let additional_licenses = detected_licenses
-                                .difference(&pkg_licenses)
-                                .collect::<BTreeSet<_>>();
-                            if !additional_licenses.is_empty() {
-                                report.warnings.push(format!(
-                                    "Package has licenses {:?}, however additional licenses were detected in source: {:?}",
-                                    pkg_licenses,
-                                    additional_licenses
-                                ));
-                            }
-                        }
-                    } else {
-                        report.errors.push(format!("Package has no licenses specified but the following licenses were detected: {:?}", detected_licenses))
-                    }
-                } else if let Some(pkg_licenses) = pkg_licenses {
-                    for pkg_license in pkg_licenses.iter() {
-                        if !suspected_licenses.contains(pkg_license) {
-                            if suspected_licenses.is_empty() {
-                                report.warnings.push(format!("Package has license '{}' which could not be detected in the source, no licenses could be detected in the source", pkg_license));
-                            } else {
-                                report.warnings.push(format!("Package has license '{}' which could not be detected in the source, suspected licenses are: {:?}", pkg_license, suspected_licenses));
-                            }
-                        }
-                    }
-                    let pkg_licenses = pkg_licenses.iter().cloned().collect::<BTreeSet<_>>();
-                    if suspected_licenses != pkg_licenses {
-                        let additional_licenses = detected_licenses
-                            .difference(&pkg_licenses)
-                            .collect::<BTreeSet<_>>();
-                        if report.warnings.is_empty() && !additional_licenses.is_empty() {
-                            report.warnings.push(format!(
-                                "Package has licenses {:?}, however additional suspected licenses were detected in source: {:?}",
-                                pkg_licenses,
-                                additional_licenses
-                            ));
-                        }
+                let scan = match self
+                    .check(self.fs_root.as_path(), url.clone(), &sha)
+                    .await?
+                {
+                    Some(scan) => scan,
+                    None => {
+                        report.warnings.push(Finding::new("SOURCE_UNAVAILABLE", format!(
+                            "Package source archive for {} is not available in the source cache or mirror, licenses were not checked as sources cannot be downloaded in offline mode",
+                            url
+                        )));
+                        return Ok(report);
                     }
-                } else {
-                    report.warnings.push(format!("Package has no license specified but the following licenses are suspected to be present in the source: {:?}", suspected_licenses))
+                };
+                compare_source_licenses(pkg_licenses.as_ref(), &scan, &mut report);
+                self.evidence.extend(scan.evidence);
+            }
+            Ok(report)
+        } else if self.license_globs.is_match(rel_path) {
+            self.license_files.push(rel_path.to_path_buf());
+            match String::from_utf8(tokio::fs::read(path).await?) {
+                Ok(file_data) => {
+                    let mut file_evidence =
+                        self.scan_license_text(file_data, rel_path, LicenseLocation::Package)?;
+                    self.evidence.append(&mut file_evidence);
+                }
+                Err(err) => {
+                    trace!("Unable to read file {}: {}", rel_path.display(), err);
                 }
-                Ok(report)
-            } else {
-                Ok(FileReport::default())
             }
+            Ok(FileReport::default())
         } else {
             Ok(FileReport::default())
         }
     }
-    async fn visit_symlink(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
+    async fn visit_symlink(&mut self, _path: &Path, _rel_path: &Path) -> Result<FileReport> {
         Ok(FileReport::default())
     }
     async fn visit_package_end(&mut self) -> Result<PackageReport> {
-        Ok(PackageReport::default())
+        let mut report = PackageReport::default();
+        let installed = LicenseScan {
+            evidence: self
+                .evidence
+                .iter()
+                .filter(|evidence| evidence.location == LicenseLocation::Package)
+                .cloned()
+                .collect(),
+        };
+        let detected_licenses = installed.detected();
+        let shipped_licenses = detected_licenses
+            .union(&installed.suspected())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if let Some(pkg_licenses) = self.declared_licenses.take() {
+            if self.license_files.is_empty() {
+                report.warnings.push(Finding::new(
+                    "LICENSE_FILES",
+                    format!(
+                        "Package has licenses '{}' but does not ship any license files",
+                        pkg_licenses
+                    ),
+                ));
+            } else if shipped_licenses.is_empty() {
+                report.warnings.push(Finding::new("LICENSE_FILES", format!(
+                    "Package has licenses '{}' but no licenses could be detected in the shipped license files: {}",
+                    pkg_licenses,
+                    self.license_files
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            } else {
+                for pkg_license in pkg_licenses.conjuncts() {
+                    if !pkg_license.is_satisfied_by(&shipped_licenses) {
+                        report.warnings.push(Finding::new("LICENSE_FILES", format!(
+                            "Package has license '{}' but does not ship its license text, licenses found in the package: {}",
+                            pkg_license,
+                            installed.describe(&shipped_licenses)
+                        )));
+                    }
+                }
+                let additional_licenses = detected_licenses
+                    .iter()
+                    .filter(|license| !pkg_licenses.mentions(license))
+                    .collect::<BTreeSet<_>>();
+                if !additional_licenses.is_empty() {
+                    report.warnings.push(Finding::new("LICENSE_FILES", format!(
+                        "Package has licenses '{}', however it ships texts of additional licenses, it may bundle third party code: {}",
+                        pkg_licenses,
+                        installed.describe(additional_licenses)
+                    )));
+                }
+            }
+        } else if !detected_licenses.is_empty() {
+            report.warnings.push(Finding::new(
+                "UNDECLARED_LICENSE",
+                format!(
+                    "Package has no licenses specified but ships the following license texts: {}",
+                    installed.describe(&detected_licenses)
+                ),
+            ));
+        }
+        self.license_files.clear();
+        report.licenses = std::mem::take(&mut self.evidence);
+        Ok(report)
+    }
+}
+
+/// Compares the declared package licenses with the licenses found in the source. Additional
+/// licenses are only reported if the declared licenses were all found, findings about the
+/// declared license identifiers themselves do not affect this.
+fn compare_source_licenses(
+    pkg_licenses: Option<&LicenseExpression>,
+    scan: &LicenseScan,
+    report: &mut FileReport,
+) {
+    let detected_licenses = scan.detected();
+    let suspected_licenses = scan.suspected();
+    let mut mismatch = false;
+    if !detected_licenses.is_empty() {
+        if let Some(pkg_licenses) = pkg_licenses {
+            for pkg_license in pkg_licenses.conjuncts() {
+                if !pkg_license.is_satisfied_by(&detected_licenses) {
+                    mismatch = true;
+                    report.warnings.push(Finding::new("SOURCE_LICENSES", format!("Package has license '{}' which was not found in the source, detected licenses: {}", pkg_license, scan.describe(&detected_licenses))))
+                }
+            }
+            if !mismatch {
+                let additional_licenses = detected_licenses
+                    .iter()
+                    .filter(|license| !pkg_licenses.mentions(license))
+                    .collect::<BTreeSet<_>>();
+                if !additional_licenses.is_empty() {
+                    report.warnings.push(Finding::new("SOURCE_LICENSES", format!(
+                        "Package has licenses '{}', however additional licenses were detected in source: {}",
+                        pkg_licenses,
+                        scan.describe(additional_licenses)
+                    )));
+                }
+            }
+        } else {
+            report.errors.push(Finding::new("UNDECLARED_LICENSE", format!("Package has no licenses specified but the following licenses were detected: {}", scan.describe(&detected_licenses))))
+        }
+    } else if let Some(pkg_licenses) = pkg_licenses {
+        for pkg_license in pkg_licenses.conjuncts() {
+            if !pkg_license.is_satisfied_by(&suspected_licenses) {
+                mismatch = true;
+                if suspected_licenses.is_empty() {
+                    report.warnings.push(Finding::new("SOURCE_LICENSES", format!("Package has license '{}' which could not be detected in the source, no licenses could be detected in the source", pkg_license)));
+                } else {
+                    report.warnings.push(Finding::new("SOURCE_LICENSES", format!("Package has license '{}' which could not be detected in the source, suspected licenses are: {}", pkg_license, scan.describe(&suspected_licenses))));
+                }
+            }
+        }
+        let additional_licenses = suspected_licenses
+            .iter()
+            .filter(|license| !pkg_licenses.mentions(license))
+            .collect::<BTreeSet<_>>();
+        if !mismatch && !additional_licenses.is_empty() {
+            report.warnings.push(Finding::new("SOURCE_LICENSES", format!(
+                "Package has licenses '{}', however additional suspected licenses were detected in source: {}",
+                pkg_licenses,
+                scan.describe(additional_licenses)
+            )));
+        }
+    } else {
+        report.warnings.push(Finding::new("UNDECLARED_LICENSE", format!("Package has no license specified but the following licenses are suspected to be present in the source: {}", scan.describe(&suspected_licenses))))
+    }
+}
+
+impl LicenseCheck {
+    /// Parses the package license expression and verifies its identifiers against the SPDX
+    /// license list. Expressions that cannot be parsed are treated as a list of licenses.
+    fn parse_license_expression(
+        &self,
+        licenses: &str,
+        report: &mut FileReport,
+    ) -> LicenseExpression {
+        let expression = match LicenseExpression::parse(licenses) {
+            Ok(expression) => expression,
+            Err(err) => {
+                report.warnings.push(Finding::new(
+                    "INVALID_EXPRESSION",
+                    format!(
+                        "Package license '{}' is not a valid SPDX license expression: {}",
+                        licenses, err
+                    ),
+                ));
+                return licenses
+                    .split_whitespace()
+                    .map(|license| LicenseExpression::License {
+                        id: license.to_owned(),
+                        or_later: false,
+                    })
+                    .reduce(|left, right| LicenseExpression::And(Box::new(left), Box::new(right)))
+                    .unwrap();
+            }
+        };
+        for id in expression
+            .licenses()
+            .iter()
+            .chain(expression.exceptions().iter())
+        {
+            if is_license_ref(id) || self.license_store.get_original(id).is_some() {
+                continue;
+            }
+            if self.deprecated_license_store.get_original(id).is_some() {
+                let replacements = [format!("{}-only", id), format!("{}-or-later", id)]
+                    .into_iter()
+                    .filter(|replacement| self.license_store.get_original(replacement).is_some())
+                    .collect::<Vec<_>>();
+                if replacements.is_empty() {
+                    report.warnings.push(Finding::new(
+                        "DEPRECATED_LICENSE",
+                        format!(
+                            "Package license '{}' is a deprecated SPDX license identifier",
+                            id
+                        ),
+                    ));
+                } else {
+                    report.warnings.push(Finding::new("DEPRECATED_LICENSE", format!(
+                        "Package license '{}' is a deprecated SPDX license identifier, use {} instead",
+                        id,
+                        replacements.join(" or ")
+                    )));
+                }
+            } else {
+                report.warnings.push(Finding::new(
+                    "UNKNOWN_LICENSE",
+                    format!(
+                        "Package license '{}' is not a known SPDX license identifier",
+                        id
+                    ),
+                ));
+            }
+        }
+        expression
     }
+
+    /// Scans the package source archive for licenses, returns `None` if the
+    /// archive is not available locally and sources cannot be downloaded.
     pub async fn check(
         &self,
         fs_root: impl AsRef<Path>,
         package_source: Url,
         package_shasum: &str,
-    ) -> Result<(BTreeSet<String>, BTreeSet<String>)> {
+    ) -> Result<Option<LicenseScan>> {
         let package_archive_name: String = package_source
             .path()
             .split('/')
@@ -312,6 +696,35 @@ impl LicenseCheck {
         }
 
         if !package_archive_is_verified {
+            if let Some(mirror_dir) = self.sources.mirror_dir.as_ref() {
+                for mirror_archive in [
+                    mirror_dir.join(&package_archive_name),
+                    mirror_dir.join(format!("{}-{}", package_shasum, &package_archive_name)),
+                ] {
+                    if !mirror_archive.is_file() {
+                        continue;
+                    }
+                    match self
+                        .verify_pkg_archive(mirror_archive.as_path(), package_shasum)
+                        .await
+                    {
+                        Ok(_) => return self.scan_package_archive(mirror_archive).map(Some),
+                        Err(err) => warn!(
+                            "Mirrored package source archive {} failed verification: {}",
+                            mirror_archive.display(),
+                            err
+                        ),
+                    }
+                }
+            }
+            if self.sources.offline {
+                debug!(
+                    "Package source archive {} not found, skipping download in offline mode",
+                    package_archive_name
+                );
+                return Ok(None);
+            }
+            let package_source = self.sources.rewrite_url(&package_source)?;
             let tmp_dir = TempDir::new("hab-auto-build-download")?;
             let tmp_package_archive = tmp_dir.path().join(&package_archive_name);
             match self
@@ -337,8 +750,9 @@ impl LicenseCheck {
                     self.scan_package_archive(tmp_package_archive)
                 }
             }
+            .map(Some)
         } else {
-            self.scan_package_archive(package_archive)
+            self.scan_package_archive(package_archive).map(Some)
         }
     }
 
@@ -433,7 +847,15 @@ impl LicenseCheck {
         let mut final_response = None;
         let mut base_headers = reqwest::header::HeaderMap::new();
         // We put a common user agent as some remote hosts forbid downloads otherwise
-        base_headers.append("User-Agent", "curl/7.68.0".parse().unwrap());
+        base_headers.append(
+            "User-Agent",
+            self.sources
+                .user_agent
+                .as_deref()
+                .unwrap_or("curl/7.68.0")
+                .parse()
+                .context("Invalid user agent for downloading sources")?,
+        );
         let mut additional_headers = reqwest::header::HeaderMap::new();
         while final_response.is_none() {
             let mut request = reqwest::Request::new(Method::GET, url.clone());
@@ -486,75 +908,207 @@ impl LicenseCheck {
             Err(anyhow!("Failed to download package archive from {}", url))
         }
     }
-    fn scan_package_archive(
-        &self,
-        package_archive: impl AsRef<Path>,
-    ) -> Result<(BTreeSet<String>, BTreeSet<String>)> {
+    fn scan_package_archive(&self, package_archive: impl AsRef<Path>) -> Result<LicenseScan> {
         debug!(
             "Scanning package archive {}",
             package_archive.as_ref().display()
         );
+        // Archives are sniffed first as upstreams sometimes serve archives with a misleading name
+        let format = self
+            .file_type_checker
+            .get_from_path(package_archive.as_ref())
+            .ok()
+            .flatten()
+            .and_then(|file_type| ArchiveFormat::from_mime_type(file_type.mime_type()))
+            .or_else(|| ArchiveFormat::from_path(package_archive.as_ref()));
         let file = std::fs::File::open(package_archive.as_ref())?;
-        let reader = std::io::BufReader::new(file);
-        match package_archive
-            .as_ref()
-            .extension()
-            .and_then(|x| x.to_str())
-        {
-            Some("bz2" | "tb2" | "tbz" | "tbz2" | "tz2") => {
-                self.scan_package_archive_contents(BzDecoder::new(reader))
-            }
-            Some("gz" | "taz" | "tgz") => {
-                self.scan_package_archive_contents(GzDecoder::new(reader))
-            }
-            Some("lzma" | "tlz") => self.scan_package_archive_contents(XzDecoder::new(reader)),
-            Some("xz" | "txz") => self.scan_package_archive_contents(XzDecoder::new(reader)),
-            Some("zst" | "tzst") => {
-                self.scan_package_archive_contents(zstd::stream::read::Decoder::new(reader)?)
-            }
-            Some("tar") => self.scan_package_archive_contents(reader),
+        let mut reader = std::io::BufReader::new(file);
+        match format {
+            Some(format) => self.scan_archive(reader, format, Path::new(""), 0),
             None => {
-                let file_type = self
-                    .file_type_checker
-                    .get_from_path(package_archive.as_ref())
-                    .ok()
-                    .unwrap_or(None)
-                    .map(|f| f.mime_type().to_string());
-                if let Some(file_type) = file_type {
-                    match file_type.as_str() {
-                        "application/gzip" => {
-                            return self.scan_package_archive_contents(GzDecoder::new(reader));
-                        }
-                        _ => {
-                            error!(
-                                "Detected archive {} as {}, unsure how to process it",
-                                package_archive.as_ref().display(),
-                                file_type
-                            );
-                        }
+                // Some plans download a single source file instead of an archive
+                let file_name = package_archive
+                    .as_ref()
+                    .file_name()
+                    .map(PathBuf::from)
+                    .unwrap_or_default();
+                let mut file_data = String::new();
+                match reader.read_to_string(&mut file_data) {
+                    Ok(_) => Ok(LicenseScan {
+                        evidence: self.scan_license_text(
+                            file_data,
+                            file_name.as_path(),
+                            LicenseLocation::Source,
+                        )?,
+                    }),
+                    Err(_) => {
+                        warn!(
+                            "Unable to detect the archive format of '{}', could not scan package archive for licenses",
+                            package_archive.as_ref().display(),
+                        );
+                        Ok(LicenseScan::default())
                     }
-                } else {
-                    error!(
-                        "Unable to detect compression for '{}', please ensure it is named correctly",
-                        package_archive.as_ref().display(),
-                    );
                 }
-                Ok((BTreeSet::default(), BTreeSet::default()))
             }
-            Some(_) => {
-                warn!("Could not scan package archive for licenses");
-                Ok((BTreeSet::default(), BTreeSet::default()))
+        }
+    }
+
+    /// Scans an archive for license files, the paths of the files found are prefixed with
+    /// the given path so that files in nested archives can be told apart.
+    fn scan_archive(
+        &self,
+        reader: impl BufRead + Seek,
+        format: ArchiveFormat,
+        prefix: &Path,
+        depth: usize,
+    ) -> Result<LicenseScan> {
+        match format {
+            ArchiveFormat::Tar(None) => self.scan_tar_contents(reader, prefix, depth),
+            ArchiveFormat::Tar(Some(Compression::Bzip2)) => {
+                self.scan_tar_contents(BzDecoder::new(reader), prefix, depth)
             }
+            ArchiveFormat::Tar(Some(Compression::Gzip)) => {
+                self.scan_tar_contents(GzDecoder::new(reader), prefix, depth)
+            }
+            ArchiveFormat::Tar(Some(Compression::Xz)) => {
+                self.scan_tar_contents(XzDecoder::new(reader), prefix, depth)
+            }
+            ArchiveFormat::Tar(Some(Compression::Zstd)) => self.scan_tar_contents(
+                zstd::stream::read::Decoder::with_buffer(reader)?,
+                prefix,
+                depth,
+            ),
+            ArchiveFormat::Zip => self.scan_zip_contents(reader, prefix, depth),
         }
     }
 
-    fn scan_package_archive_contents(
+    fn scan_tar_contents(
         &self,
         decoder: impl Read,
-    ) -> Result<(BTreeSet<String>, BTreeSet<String>)> {
-        let mut licenses = BTreeSet::default();
-        let mut suspected_licenses = BTreeSet::default();
+        prefix: &Path,
+        depth: usize,
+    ) -> Result<LicenseScan> {
+        let mut scan = LicenseScan::default();
         let mut tar = Archive::new(decoder);
+        for entry in tar.entries()? {
+            match entry {
+                Ok(entry) => {
+                    if !entry.header().entry_type().is_file() {
+                        trace!("Skipping entry {} in archive", entry.path()?.display());
+                        continue;
+                    }
+                    let entry_path = entry.path()?.to_path_buf();
+                    self.scan_archive_entry(&entry_path, entry, prefix, depth, &mut scan)?;
+                }
+                Err(err) => {
+                    error!("Error reading entries: {}", err);
+                }
+            }
+        }
+        Ok(scan)
+    }
+
+    fn scan_zip_contents(
+        &self,
+        reader: impl Read + Seek,
+        prefix: &Path,
+        depth: usize,
+    ) -> Result<LicenseScan> {
+        let mut scan = LicenseScan::default();
+        let mut zip = ZipArchive::new(reader)?;
+        for index in 0..zip.len() {
+            match zip.by_index(index) {
+                Ok(entry) => {
+                    if !entry.is_file() {
+                        trace!("Skipping entry {} in archive", entry.name());
+                        continue;
+                    }
+                    let Some(entry_path) = entry.enclosed_name().map(Path::to_path_buf) else {
+                        warn!(
+                            "Skipping entry {} with an unsafe path in archive",
+                            entry.name()
+                        );
+                        continue;
+                    };
+                    self.scan_archive_entry(&entry_path, entry, prefix, depth, &mut scan)?;
+                }
+                Err(err) => {
+                    error!("Error reading entries: {}", err);
+                }
+            }
+        }
+        Ok(scan)
+    }
+
+    fn scan_archive_entry(
+        &self,
+        entry_path: &Path,
+        mut entry: impl Read,
+        prefix: &Path,
+        depth: usize,
+        scan: &mut LicenseScan,
+    ) -> Result<()> {
+        let path = prefix.join(entry_path);
+        if depth < MAX_NESTED_ARCHIVE_DEPTH {
+            if let Some(format) = ArchiveFormat::from_path(entry_path) {
+                let mut data = Vec::new();
+                (&mut entry)
+                    .take(MAX_NESTED_ARCHIVE_SIZE + 1)
+                    .read_to_end(&mut data)?;
+                if data.len() as u64 > MAX_NESTED_ARCHIVE_SIZE {
+                    trace!(
+                        "Skipping nested archive {} larger than {} bytes",
+                        path.display(),
+                        MAX_NESTED_ARCHIVE_SIZE
+                    );
+                    return Ok(());
+                }
+                trace!("Checking nested archive {}", path.display());
+                let format = self
+                    .file_type_checker
+                    .get(&data)
+                    .and_then(|file_type| ArchiveFormat::from_mime_type(file_type.mime_type()))
+                    .unwrap_or(format);
+                match self.scan_archive(std::io::Cursor::new(data), format, &path, depth + 1) {
+                    Ok(mut nested_scan) => scan.evidence.append(&mut nested_scan.evidence),
+                    Err(err) => debug!(
+                        "Unable to scan nested archive {}: {:#}",
+                        path.display(),
+                        err
+                    ),
+                }
+                return Ok(());
+            }
+        }
+        if self.license_globs.is_match(entry_path) {
+            trace!("Checking entry {} in archive", path.display());
+            let mut file_data = String::new();
+            match entry.read_to_string(&mut file_data) {
+                Ok(_) => {
+                    scan.evidence.append(&mut self.scan_license_text(
+                        file_data,
+                        path.as_path(),
+                        LicenseLocation::Source,
+                    )?);
+                }
+                Err(err) => {
+                    trace!("Unable to read file {}: {}", path.display(), err);
+                }
+            };
+        } else {
+            trace!("Skipping entry {} in archive", path.display());
+        }
+        Ok(())
+    }
+
+    /// Scans the text of a single license file, falling back to a low confidence scan if
+    /// nothing is found and doing a deeper scan if the file contains many licenses.
+    fn scan_license_text(
+        &self,
+        file_data: String,
+        path: &Path,
+        location: LicenseLocation,
+    ) -> Result<Vec<LicenseEvidence>> {
         let scan_strategies = vec![
             ScanStrategy::new(&self.license_store)
                 .confidence_threshold(0.8)
@@ -591,99 +1145,91 @@ impl LicenseCheck {
                 .max_passes(5)
                 .optimize(true),
         ];
-        for entry in tar.entries()? {
-            match entry {
-                Ok(entry) => {
-                    if !entry.header().entry_type().is_file() {
-                        trace!("Skipping entry {} in archive", entry.path()?.display());
-                        continue;
-                    }
-                    let entry_path = entry.path()?.to_path_buf();
-                    if self.license_globs.is_match(entry_path.as_path()) {
-                        trace!("Checking entry {} in archive", entry.path()?.display());
-                        let mut reader = std::io::BufReader::new(entry);
-
-                        let mut file_data = String::new();
-                        match reader.read_to_string(&mut file_data) {
-                            Ok(_) => {
-                                let data: TextData = file_data.into();
-                                let mut file_licenses = BTreeSet::new();
-                                for strategy in scan_strategies.iter() {
-                                    let results = strategy.scan(&data)?;
-                                    for item in results.containing {
-                                        file_licenses.insert(item.license.name.to_string());
-                                        debug!(
-                                            "{} detected in {}",
-                                            item.license.name,
-                                            entry_path.display()
-                                        );
-                                    }
-                                }
-                                if file_licenses.is_empty() {
-                                    // Do a lower quality scan if we haven't detected any licenses yet
-                                    for strategy in suspect_scan_strategies.iter() {
-                                        let results = strategy.scan(&data)?;
-                                        for item in results.containing {
-                                            file_licenses.insert(item.license.name.to_string());
-                                            debug!(
-                                                "{} suspected in {}",
-                                                item.license.name,
-                                                entry_path.display()
-                                            );
-                                        }
-                                    }
-                                    suspected_licenses.append(&mut file_licenses);
-                                } else if file_licenses.len() >= 5 {
-                                    // Do a more costly scan for licenses if we find a lot of them
-                                    for strategy in deep_scan_strategies.iter() {
-                                        let results = strategy.scan(&data)?;
-                                        for item in results.containing {
-                                            file_licenses.insert(item.license.name.to_string());
-                                            debug!(
-                                                "{} detected in {}",
-                                                item.license.name,
-                                                entry_path.display()
-                                            );
-                                        }
-                                    }
-                                }
-                                licenses.append(&mut file_licenses);
-                            }
-                            Err(err) => {
-                                trace!("Unable to read file {}: {}", entry_path.display(), err);
-                            }
-                        };
-                    } else {
-                        trace!("Skipping entry {} in archive", entry.path()?.display());
-                    }
+        let data: TextData = file_data.into();
+        let mut file_evidence = Vec::new();
+        for strategy in scan_strategies.iter() {
+            let results = strategy.scan(&data)?;
+            for item in results.containing {
+                debug!("{} detected in {}", item.license.name, path.display());
+                file_evidence.push(LicenseEvidence {
+                    license: item.license.name.to_string(),
+                    path: path.to_path_buf(),
+                    score: item.score,
+                    strategy: LicenseScanStrategy::Normal,
+                    location,
+                });
+            }
+        }
+        if file_evidence.is_empty() {
+            // Do a lower quality scan if we haven't detected any licenses yet
+            for strategy in suspect_scan_strategies.iter() {
+                let results = strategy.scan(&data)?;
+                for item in results.containing {
+                    debug!("{} suspected in {}", item.license.name, path.display());
+                    file_evidence.push(LicenseEvidence {
+                        license: item.license.name.to_string(),
+                        path: path.to_path_buf(),
+                        score: item.score,
+                        strategy: LicenseScanStrategy::Suspect,
+                        location,
+                    });
                 }
-                Err(err) => {
-                    error!("Error reading entries: {}", err);
+            }
+        } else if file_evidence
+            .iter()
+            .map(|evidence| evidence.license.as_str())
+            .collect::<BTreeSet<_>>()
+            .len()
+            >= 5
+        {
+            // Do a more costly scan for licenses if we find a lot of them
+            for strategy in deep_scan_strategies.iter() {
+                let results = strategy.scan(&data)?;
+                for item in results.containing {
+                    debug!("{} detected in {}", item.license.name, path.display());
+                    file_evidence.push(LicenseEvidence {
+                        license: item.license.name.to_string(),
+                        path: path.to_path_buf(),
+                        score: item.score,
+                        strategy: LicenseScanStrategy::Deep,
+                        location,
+                    });
                 }
             }
         }
-        Ok((licenses, suspected_licenses))
+        Ok(file_evidence)
     }
 }
 
 pub struct ArtifactChecker<'a> {
     artifact: PackageArtifact,
+    contents: &'a PackageArtifactContents,
     checks: Vec<Check<'a>>,
 }
 
 impl<'a> ArtifactChecker<'a> {
     pub async fn new(
         artifact: PackageArtifact,
+        contents: &'a PackageArtifactContents,
         metadata: &'a PackageMetadata,
         fs_root: impl AsRef<Path>,
+        registry: &CheckRegistry,
     ) -> Result<ArtifactChecker<'a>> {
-        let checks = vec![
-            Check::EmptyTopLevelDir(EmptyTopLevelDirCheck::default()),
-            Check::Dependency(DependencyCheck::new(metadata)),
-            Check::LicenseCheck(LicenseCheck::new(fs_root)?),
-        ];
+        let checks = registry.create(&CheckContext {
+            artifact: &artifact,
+            contents,
+            metadata,
+            fs_root: fs_root.as_ref(),
+            sources: &registry.sources,
+            platform_shells: &registry.platform_shells,
+            regression_thresholds: &registry.regression_thresholds,
+        })?;
 
-        Ok(ArtifactChecker { artifact, checks })
+        Ok(ArtifactChecker {
+            artifact,
+            contents,
+            checks,
+        })
     }
 
     pub async fn check(&mut self) -> Result<ArtifactReport> {
@@ -691,82 +1237,92 @@ impl<'a> ArtifactChecker<'a> {
             self.artifact.ident.borrow().into(),
             self.artifact.path.clone(),
         );
-        let mut next_dirs = VecDeque::new();
-        let install_dir = self.artifact.install_dir();
-        next_dirs.push_back(install_dir.clone());
-        while !next_dirs.is_empty() {
-            let current_dir = next_dirs.pop_front().unwrap();
-            trace!("Checking {}", current_dir.display());
-            let rel_current_dir = current_dir
-                .as_path()
-                .strip_prefix(install_dir.as_path())
-                .unwrap();
-            let mut read_dir = tokio::fs::read_dir(current_dir.as_path())
-                .await
-                .with_context(|| anyhow!("Failed to read directory {}", current_dir.display()))?;
+        // Group the artifact entries by their parent directory, adding any
+        // directories that are only implied by the paths of their children
+        let mut dir_entries: BTreeMap<PathBuf, Vec<(PathBuf, &ArtifactEntryType)>> =
+            BTreeMap::new();
+        let mut known_dirs = HashSet::new();
+        for entry in self.contents.entries.iter() {
+            let mut path = entry.path.clone();
+            let mut entry_type = &entry.entry_type;
+            loop {
+                let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                if matches!(entry_type, ArtifactEntryType::Dir) && !known_dirs.insert(path.clone())
+                {
+                    break;
+                }
+                dir_entries
+                    .entry(parent.clone())
+                    .or_default()
+                    .push((path, entry_type));
+                if parent.components().count() == 0 || known_dirs.contains(&parent) {
+                    break;
+                }
+                path = parent;
+                entry_type = &ArtifactEntryType::Dir;
+            }
+        }
+        let install_dir = self.contents.install_dir.as_path();
+        let mut next_dirs = VecDeque::new();
+        next_dirs.push_back(PathBuf::new());
+        while let Some(rel_current_dir) = next_dirs.pop_front() {
+            let current_dir = install_dir.join(rel_current_dir.as_path());
+            trace!("Checking {}", current_dir.display());
             for check in self.checks.iter_mut() {
                 report.dir_report_append(
-                    rel_current_dir,
+                    rel_current_dir.as_path(),
                     check
-                        .visit_dir_start(current_dir.as_path(), rel_current_dir)
+                        .visit_dir_start(current_dir.as_path(), rel_current_dir.as_path())
                         .await?,
                 );
             }
-            while let Some(entry) = read_dir.next_entry().await.with_context(|| {
-                anyhow!(
-                    "Failed to read next entry from directory {}",
-                    current_dir.display()
-                )
-            })? {
-                let entry_path = entry.path();
-                let entry_metadata = entry.metadata().await.with_context(|| {
-                    anyhow!(
-                        "Failed to directory entry metadata {}",
-                        entry_path.display()
-                    )
-                })?;
-                let rel_entry_path = entry_path
-                    .as_path()
-                    .strip_prefix(install_dir.as_path())
-                    .unwrap();
-
-                if entry_metadata.is_dir() {
-                    for check in self.checks.iter_mut() {
-                        report.dir_report_append(
-                            rel_entry_path,
-                            check
-                                .visit_child_dir(entry_path.as_path(), rel_entry_path)
-                                .await?,
-                        );
+            for (rel_entry_path, entry_type) in dir_entries
+                .get(&rel_current_dir)
+                .map(|entries| entries.as_slice())
+                .unwrap_or_default()
+            {
+                let entry_path = install_dir.join(rel_entry_path);
+                match entry_type {
+                    ArtifactEntryType::Dir => {
+                        for check in self.checks.iter_mut() {
+                            report.dir_report_append(
+                                rel_entry_path,
+                                check
+                                    .visit_child_dir(entry_path.as_path(), rel_entry_path)
+                                    .await?,
+                            );
+                        }
+                        next_dirs.push_back(rel_entry_path.clone());
                     }
-                    next_dirs.push_back(entry.path());
-                } else if entry_metadata.is_file() {
-                    trace!("Checking {}", entry_path.display());
-                    for check in self.checks.iter_mut() {
-                        report.file_report_append(
-                            rel_entry_path,
-                            check
-                                .visit_file(entry_path.as_path(), rel_entry_path)
-                                .await?,
-                        );
+                    ArtifactEntryType::File => {
+                        trace!("Checking {}", entry_path.display());
+                        for check in self.checks.iter_mut() {
+                            report.file_report_append(
+                                rel_entry_path,
+                                check
+                                    .visit_file(entry_path.as_path(), rel_entry_path)
+                                    .await?,
+                            );
+                        }
                     }
-                } else if entry_metadata.is_symlink() {
-                    trace!("Checking symlink {}", entry_path.display());
-                    for check in self.checks.iter_mut() {
-                        report.file_report_append(
-                            rel_entry_path,
-                            check
-                                .visit_symlink(entry_path.as_path(), rel_entry_path)
-                                .await?,
-                        );
+                    ArtifactEntryType::Symlink(_) => {
+                        trace!("Checking symlink {}", entry_path.display());
+                        for check in self.checks.iter_mut() {
+                            report.file_report_append(
+                                rel_entry_path,
+                                check
+                                    .visit_symlink(entry_path.as_path(), rel_entry_path)
+                                    .await?,
+                            );
+                        }
                     }
                 }
             }
             for check in self.checks.iter_mut() {
                 report.dir_report_append(
-                    rel_current_dir,
+                    rel_current_dir.as_path(),
                     check
-                        .visit_dir_end(current_dir.as_path(), rel_current_dir)
+                        .visit_dir_end(current_dir.as_path(), rel_current_dir.as_path())
                         .await?,
                 );
             }
@@ -778,137 +1334,506 @@ impl<'a> ArtifactChecker<'a> {
     }
 }
 
-pub enum Check<'a> {
-    EmptyTopLevelDir(EmptyTopLevelDirCheck),
-    LicenseCheck(LicenseCheck),
-    Dependency(DependencyCheck<'a>),
+/// Severity with which the findings of a check are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckSeverity {
+    Error,
+    Warning,
+    Off,
 }
 
-impl<'a> Check<'a> {
-    fn id(&self) -> &'static str {
+impl std::fmt::Display for CheckSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Check::EmptyTopLevelDir(check) => check.id(),
-            Check::Dependency(check) => check.id(),
-            Check::LicenseCheck(check) => check.id(),
+            CheckSeverity::Error => f.pad("error"),
+            CheckSeverity::Warning => f.pad("warning"),
+            CheckSeverity::Off => f.pad("off"),
         }
     }
-    fn description(&self) -> &'static str {
-        match self {
-            Check::EmptyTopLevelDir(check) => check.description(),
-            Check::Dependency(check) => check.description(),
-            Check::LicenseCheck(check) => check.description(),
+}
+
+#[derive(Debug)]
+pub struct CheckMetadata {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub default_severity: CheckSeverity,
+    /// Ids of the rules that identify the kinds of findings the check reports
+    pub rules: &'static [&'static str],
+}
+
+/// A single issue reported by a check. The rule identifies the kind of issue
+/// so its severity can be overridden as `CHECK_ID.RULE`.
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    check: &'static str,
+    rule: &'static str,
+    message: String,
+}
+
+impl Finding {
+    pub fn new(rule: &'static str, message: impl Into<String>) -> Finding {
+        Finding {
+            check: "",
+            rule,
+            message: message.into(),
         }
     }
-    async fn visit_dir_start(
-        &mut self,
-        abs_path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
-        match self {
-            Check::EmptyTopLevelDir(ref mut check) => {
-                check.visit_dir_start(&abs_path, &rel_path).await
-            }
-            Check::Dependency(ref mut check) => check.visit_dir_start(&abs_path, &rel_path).await,
-            Check::LicenseCheck(ref mut check) => check.visit_dir_start(&abs_path, &rel_path).await,
-        }
-        .with_context(|| {
-            anyhow!(
-                "Check {} failed while entering directory '{}'",
-                self.id(),
-                abs_path.as_ref().display()
-            )
-        })
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}.{}]", self.message, self.check, self.rule)
     }
-    async fn visit_dir_end(
-        &mut self,
-        abs_path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
-        match self {
-            Check::EmptyTopLevelDir(ref mut check) => {
-                check.visit_dir_end(&abs_path, &rel_path).await
+}
+
+/// Everything a check may need to know about the artifact it is created for,
+/// checks may only hold on to the artifact contents and metadata.
+pub struct CheckContext<'a, 'b> {
+    pub artifact: &'b PackageArtifact,
+    pub contents: &'a PackageArtifactContents,
+    pub metadata: &'a PackageMetadata,
+    pub fs_root: &'b Path,
+    pub sources: &'b SourceConfiguration,
+    pub platform_shells: &'b [PathBuf],
+    pub regression_thresholds: &'b RegressionThresholds,
+}
+
+/// A check that is run against the contents of a package artifact.
+///
+/// The artifact checker walks the installed package directory breadth first,
+/// calling `visit_dir_start` when entering a directory, `visit_child_dir`,
+/// `visit_file` or `visit_symlink` for each of its entries and `visit_dir_end`
+/// when leaving it. `visit_package_end` is called once all directories have
+/// been visited.
+#[async_trait]
+pub trait ArtifactCheck: Send {
+    fn metadata(&self) -> &'static CheckMetadata;
+    async fn visit_dir_start(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<DirReport> {
+        Ok(DirReport::default())
+    }
+    async fn visit_dir_end(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<DirReport> {
+        Ok(DirReport::default())
+    }
+    async fn visit_child_dir(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<DirReport> {
+        Ok(DirReport::default())
+    }
+    async fn visit_file(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<FileReport> {
+        Ok(FileReport::default())
+    }
+    async fn visit_symlink(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<FileReport> {
+        Ok(FileReport::default())
+    }
+    async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        Ok(PackageReport::default())
+    }
+}
+
+type CheckConstructor =
+    for<'a, 'b> fn(&CheckContext<'a, 'b>) -> Result<Box<dyn ArtifactCheck + 'a>>;
+
+struct CheckRegistration {
+    metadata: &'static CheckMetadata,
+    create: CheckConstructor,
+    enabled: bool,
+    requested: bool,
+}
+
+/// Overrides the severity of a check or of one of its rules for a set of packages
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CheckConfiguration {
+    /// Id of the check to configure as `CHECK_ID`, or of a single rule as `CHECK_ID.RULE`
+    pub check: String,
+    /// Patterns matching the packages in the form `origin/name`, applies to all packages if missing
+    pub packages: Option<Vec<String>>,
+    pub severity: CheckSeverity,
+}
+
+struct CheckSeverityOverride {
+    check: String,
+    rule: Option<String>,
+    packages: Option<Vec<glob::Pattern>>,
+    severity: CheckSeverity,
+}
+
+/// The set of checks available to the artifact checker
+pub struct CheckRegistry {
+    registrations: Vec<CheckRegistration>,
+    overrides: Vec<CheckSeverityOverride>,
+    sources: SourceConfiguration,
+    platform_shells: Vec<PathBuf>,
+    regression_thresholds: RegressionThresholds,
+}
+
+impl Default for CheckRegistry {
+    fn default() -> Self {
+        let mut registry = CheckRegistry {
+            registrations: Vec::new(),
+            overrides: Vec::new(),
+            sources: SourceConfiguration::default(),
+            platform_shells: PLATFORM_SHELLS.clone(),
+            regression_thresholds: RegressionThresholds::default(),
+        };
+        registry.register(&EmptyTopLevelDirCheck::METADATA, |_| {
+            Ok(Box::<EmptyTopLevelDirCheck>::default())
+        });
+        registry.register(&DependencyCheck::METADATA, |context| {
+            Ok(Box::new(DependencyCheck::new(
+                context.metadata,
+                context.contents.root.as_path(),
+                context.artifact.install_dir(),
+                context.platform_shells.to_vec(),
+            )))
+        });
+        registry.register(&LicenseCheck::METADATA, |context| {
+            Ok(Box::new(LicenseCheck::new(
+                context.fs_root,
+                context.sources.clone(),
+            )?))
+        });
+        registry.register(&HardcodedPathCheck::METADATA, |context| {
+            Ok(Box::new(HardcodedPathCheck::new(
+                context.metadata,
+                context.platform_shells.to_vec(),
+            )))
+        });
+        registry.register(&PkgConfigCheck::METADATA, |context| {
+            Ok(Box::new(PkgConfigCheck::new(
+                context.metadata,
+                context.contents.root.as_path(),
+                context.artifact.install_dir(),
+            )))
+        });
+        registry.register(&PermissionsCheck::METADATA, |context| {
+            Ok(Box::new(PermissionsCheck::new(
+                context.metadata,
+                context.contents,
+                context.artifact.install_dir(),
+            )))
+        });
+        registry.register(&MetafileCheck::METADATA, |context| {
+            Ok(Box::new(MetafileCheck::new(
+                context.artifact.ident.clone(),
+                context.contents.root.as_path(),
+                context.artifact.install_dir(),
+            )))
+        });
+        registry.register(&FileConflictCheck::METADATA, |context| {
+            Ok(Box::new(FileConflictCheck::new(
+                context.metadata,
+                context.contents.root.as_path(),
+            )))
+        });
+        registry.register(&RegressionCheck::METADATA, |context| {
+            Ok(Box::new(RegressionCheck::new(
+                context.artifact,
+                context.regression_thresholds.clone(),
+            )))
+        });
+        registry
+    }
+}
+
+impl CheckRegistry {
+    pub fn register(&mut self, metadata: &'static CheckMetadata, create: CheckConstructor) {
+        self.registrations.push(CheckRegistration {
+            metadata,
+            create,
+            enabled: true,
+            requested: false,
+        });
+    }
+
+    fn registration_mut(&mut self, id: &str) -> Result<&mut CheckRegistration> {
+        self.registrations
+            .iter_mut()
+            .find(|registration| registration.metadata.id == id)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown check '{}', use --list-checks to see all checks",
+                    id
+                )
+            })
+    }
+
+    /// Applies the severity overrides from the hab auto build configuration,
+    /// later entries take precedence over earlier ones and overrides of a rule
+    /// take precedence over overrides of its check.
+    pub fn configure(&mut self, checks: &[CheckConfiguration]) -> Result<()> {
+        for check in checks.iter() {
+            let (id, rule) = match check.check.split_once('.') {
+                Some((id, rule)) => (id, Some(rule)),
+                None => (check.check.as_str(), None),
+            };
+            let registration = self.registration_mut(id)?;
+            if let Some(rule) = rule {
+                if !registration.metadata.rules.contains(&rule) {
+                    return Err(anyhow!(
+                        "Unknown rule '{}' of check {}, use --list-checks to see all rules",
+                        rule,
+                        id
+                    ));
+                }
             }
-            Check::Dependency(ref mut check) => check.visit_dir_end(&abs_path, &rel_path).await,
-            Check::LicenseCheck(ref mut check) => check.visit_dir_end(&abs_path, &rel_path).await,
+            let packages = match check.packages.as_ref() {
+                Some(patterns) => Some(
+                    patterns
+                        .iter()
+                        .map(|pattern| {
+                            glob::Pattern::new(pattern).with_context(|| {
+                                format!(
+                                    "Invalid package pattern '{}' for check {}",
+                                    pattern, check.check
+                                )
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                None => None,
+            };
+            self.overrides.push(CheckSeverityOverride {
+                check: id.to_string(),
+                rule: rule.map(String::from),
+                packages,
+                severity: check.severity,
+            });
         }
-        .with_context(|| {
-            anyhow!(
-                "Check {} failed while exiting directory '{}'",
-                self.id(),
-                abs_path.as_ref().display()
-            )
-        })
+        Ok(())
     }
-    async fn visit_child_dir(
-        &mut self,
-        abs_path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
-        match self {
-            Check::EmptyTopLevelDir(ref mut check) => {
-                check.visit_child_dir(&abs_path, &rel_path).await
+
+    pub fn set_sources(&mut self, sources: SourceConfiguration) {
+        self.sources = sources;
+    }
+
+    pub fn sources(&self) -> &SourceConfiguration {
+        &self.sources
+    }
+
+    /// Replaces the host interpreters that scripts in standard packages may use
+    pub fn set_platform_shells(&mut self, platform_shells: Vec<PathBuf>) {
+        self.platform_shells = platform_shells;
+    }
+
+    pub fn set_regression_thresholds(&mut self, regression_thresholds: RegressionThresholds) {
+        self.regression_thresholds = regression_thresholds;
+    }
+
+    /// Restricts the checks that are run, an empty list of checks to run means all checks
+    pub fn select(&mut self, only: &[String], skip: &[String]) -> Result<()> {
+        if !only.is_empty() {
+            for registration in self.registrations.iter_mut() {
+                registration.enabled = false;
+            }
+            for id in only.iter() {
+                let registration = self.registration_mut(id)?;
+                registration.enabled = true;
+                registration.requested = true;
             }
-            Check::Dependency(ref mut check) => check.visit_child_dir(&abs_path, &rel_path).await,
-            Check::LicenseCheck(ref mut check) => check.visit_child_dir(&abs_path, &rel_path).await,
         }
-        .with_context(|| {
-            anyhow!(
-                "Check {} failed while checking child directory '{}'",
-                self.id(),
-                &abs_path.as_ref().display()
-            )
-        })
+        for id in skip.iter() {
+            self.registration_mut(id)?.enabled = false;
+        }
+        Ok(())
     }
-    async fn visit_file(
-        &mut self,
-        abs_path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
-        match self {
-            Check::EmptyTopLevelDir(ref mut check) => check.visit_file(&abs_path, &rel_path).await,
-            Check::Dependency(ref mut check) => check.visit_file(&abs_path, &rel_path).await,
-            Check::LicenseCheck(ref mut check) => check.visit_file(&abs_path, &rel_path).await,
-        }
-        .with_context(|| {
-            anyhow!(
-                "Check {} failed while checking file '{}'",
-                self.id(),
-                &abs_path.as_ref().display()
-            )
-        })
+
+    fn severity_override(
+        &self,
+        id: &str,
+        rule: Option<&str>,
+        ident: &PackageIdent,
+    ) -> Option<CheckSeverity> {
+        let package = format!("{}/{}", ident.origin, ident.name);
+        self.overrides
+            .iter()
+            .rev()
+            .find(|severity_override| {
+                severity_override.check == id
+                    && severity_override.rule.as_deref() == rule
+                    && severity_override.packages.as_ref().is_none_or(|patterns| {
+                        patterns.iter().any(|pattern| pattern.matches(&package))
+                    })
+            })
+            .map(|severity_override| severity_override.severity)
     }
 
-    async fn visit_symlink(
-        &mut self,
-        abs_path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
-        match self {
-            Check::EmptyTopLevelDir(ref mut check) => {
-                check.visit_symlink(&abs_path, &rel_path).await
+    pub fn print(&self) {
+        for registration in self.registrations.iter() {
+            println!(
+                "{:<24} {:<8} {}",
+                registration.metadata.id.bold(),
+                registration.metadata.default_severity,
+                registration.metadata.description
+            );
+            println!(
+                "{:<24} {:<8} rules: {}",
+                "",
+                "",
+                registration.metadata.rules.join(", ")
+            );
+        }
+    }
+
+    fn create<'a>(&self, context: &CheckContext<'a, '_>) -> Result<Vec<Check<'a>>> {
+        let ident: PackageIdent = context.artifact.ident.borrow().into();
+        let mut checks = Vec::new();
+        for registration in self.registrations.iter() {
+            if !registration.enabled {
+                continue;
+            }
+            let severity = self.severity_override(registration.metadata.id, None, &ident);
+            let rule_severities = registration
+                .metadata
+                .rules
+                .iter()
+                .filter_map(|rule| {
+                    self.severity_override(registration.metadata.id, Some(rule), &ident)
+                        .map(|severity| (*rule, severity))
+                })
+                .collect::<HashMap<_, _>>();
+            // A check that is turned off still runs if some of its rules are turned on
+            let disabled = match severity {
+                Some(severity) => severity == CheckSeverity::Off,
+                None => {
+                    registration.metadata.default_severity == CheckSeverity::Off
+                        && !registration.requested
+                }
+            } && rule_severities
+                .values()
+                .all(|severity| *severity == CheckSeverity::Off);
+            if disabled {
+                debug!(
+                    "Check {} is turned off for {}",
+                    registration.metadata.id, ident
+                );
+                continue;
             }
-            Check::Dependency(ref mut check) => check.visit_symlink(&abs_path, &rel_path).await,
-            Check::LicenseCheck(ref mut check) => check.visit_symlink(&abs_path, &rel_path).await,
+            checks.push(Check {
+                check: (registration.create)(context).with_context(|| {
+                    anyhow!("Failed to create check {}", registration.metadata.id)
+                })?,
+                severity,
+                rule_severities,
+            });
         }
-        .with_context(|| {
-            anyhow!(
-                "Check {} failed while checking symlink '{}'",
-                self.id(),
-                &abs_path.as_ref().display()
+        Ok(checks)
+    }
+}
+
+/// Wraps a check to add context to the errors it raises and apply
+/// any configured severity override to its reports
+struct Check<'a> {
+    check: Box<dyn ArtifactCheck + 'a>,
+    severity: Option<CheckSeverity>,
+    rule_severities: HashMap<&'static str, CheckSeverity>,
+}
+
+impl<'a> Check<'a> {
+    fn id(&self) -> &'static str {
+        self.check.metadata().id
+    }
+    /// Tags the findings with the check id and moves each one to the errors or warnings
+    /// according to the severity override of its rule or else of the check, findings
+    /// that are turned off are dropped.
+    fn apply_severity(&self, errors: &mut Vec<Finding>, warnings: &mut Vec<Finding>) {
+        let findings = errors
+            .drain(..)
+            .map(|finding| (CheckSeverity::Error, finding))
+            .chain(
+                warnings
+                    .drain(..)
+                    .map(|finding| (CheckSeverity::Warning, finding)),
             )
-        })
+            .collect::<Vec<_>>();
+        for (severity, mut finding) in findings {
+            finding.check = self.id();
+            let severity = self
+                .rule_severities
+                .get(finding.rule)
+                .copied()
+                .or(self.severity)
+                .unwrap_or(severity);
+            match severity {
+                CheckSeverity::Error => errors.push(finding),
+                CheckSeverity::Warning => warnings.push(finding),
+                CheckSeverity::Off => {}
+            }
+        }
+    }
+    async fn visit_dir_start(&mut self, abs_path: &Path, rel_path: &Path) -> Result<DirReport> {
+        self.check
+            .visit_dir_start(abs_path, rel_path)
+            .await
+            .map(|report| report.with_severity(self))
+            .with_context(|| {
+                anyhow!(
+                    "Check {} failed while entering directory '{}'",
+                    self.id(),
+                    abs_path.display()
+                )
+            })
+    }
+    async fn visit_dir_end(&mut self, abs_path: &Path, rel_path: &Path) -> Result<DirReport> {
+        self.check
+            .visit_dir_end(abs_path, rel_path)
+            .await
+            .map(|report| report.with_severity(self))
+            .with_context(|| {
+                anyhow!(
+                    "Check {} failed while exiting directory '{}'",
+                    self.id(),
+                    abs_path.display()
+                )
+            })
+    }
+    async fn visit_child_dir(&mut self, abs_path: &Path, rel_path: &Path) -> Result<DirReport> {
+        self.check
+            .visit_child_dir(abs_path, rel_path)
+            .await
+            .map(|report| report.with_severity(self))
+            .with_context(|| {
+                anyhow!(
+                    "Check {} failed while checking child directory '{}'",
+                    self.id(),
+                    abs_path.display()
+                )
+            })
+    }
+    async fn visit_file(&mut self, abs_path: &Path, rel_path: &Path) -> Result<FileReport> {
+        self.check
+            .visit_file(abs_path, rel_path)
+            .await
+            .map(|report| report.with_severity(self))
+            .with_context(|| {
+                anyhow!(
+                    "Check {} failed while checking file '{}'",
+                    self.id(),
+                    abs_path.display()
+                )
+            })
+    }
+    async fn visit_symlink(&mut self, abs_path: &Path, rel_path: &Path) -> Result<FileReport> {
+        self.check
+            .visit_symlink(abs_path, rel_path)
+            .await
+            .map(|report| report.with_severity(self))
+            .with_context(|| {
+                anyhow!(
+                    "Check {} failed while checking symlink '{}'",
+                    self.id(),
+                    abs_path.display()
+                )
+            })
     }
     async fn visit_package_end(&mut self) -> Result<PackageReport> {
-        match self {
-            Check::EmptyTopLevelDir(ref mut check) => check.visit_package_end().await,
-            Check::Dependency(ref mut check) => check.visit_package_end().await,
-            Check::LicenseCheck(ref mut check) => check.visit_package_end().await,
-        }
-        .with_context(|| anyhow!("Check {} failed while completing package checks", self.id()))
+        self.check
+            .visit_package_end()
+            .await
+            .map(|report| report.with_severity(self))
+            .with_context(|| anyhow!("Check {} failed while completing package checks", self.id()))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ArtifactReport {
     ident: PackageIdent,
     file_path: ValidFilePath,
@@ -943,6 +1868,12 @@ impl ArtifactReport {
     }
 
     fn package_report_append(&mut self, mut package_report: PackageReport) {
+        self.package_issues
+            .licenses
+            .append(&mut package_report.licenses);
+        self.package_issues
+            .libraries
+            .append(&mut package_report.libraries);
         if matches!(package_report.status(), ReportStatus::Ok) {
             return;
         }
@@ -995,13 +1926,20 @@ impl ArtifactReport {
             })
             .or_insert(file_report);
     }
-    pub fn print(&self, only_summary: bool) {
+    pub fn print(&self, format: ReportFormat, only_summary: bool) -> Result<()> {
+        if matches!(format, ReportFormat::Json) {
+            println!(
+                "{}",
+                serde_json::to_string(self).context("Failed to serialize check report")?
+            );
+            return Ok(());
+        }
         if !only_summary {
             for error in self.package_issues.errors.iter() {
-                println!("{}: {}", self.ident, error.red());
+                println!("{}: {}", self.ident, error.to_string().red());
             }
             for warning in self.package_issues.warnings.iter() {
-                println!("{}: {}", self.ident, warning.yellow());
+                println!("{}: {}", self.ident, warning.to_string().yellow());
             }
 
             for (dir_path, dir_report) in self.dir_issues.iter() {
@@ -1017,7 +1955,7 @@ impl ArtifactReport {
                         "{}: {} - {}",
                         self.ident,
                         dir_path.display().to_string().blue().bold(),
-                        error.red()
+                        error.to_string().red()
                     );
                 }
                 for warning in dir_report.warnings.iter() {
@@ -1025,7 +1963,7 @@ impl ArtifactReport {
                         "{}: {} - {}",
                         self.ident,
                         dir_path.display().to_string().blue().bold(),
-                        warning.yellow()
+                        warning.to_string().yellow()
                     );
                 }
             }
@@ -1041,7 +1979,7 @@ impl ArtifactReport {
                         "{}: {} - {}",
                         self.ident,
                         file_path.display().to_string().white(),
-                        error.red()
+                        error.to_string().red()
                     );
                 }
                 for warning in file_report.warnings.iter() {
@@ -1049,7 +1987,7 @@ impl ArtifactReport {
                         "{}: {} - {}",
                         self.ident,
                         file_path.display().to_string().white(),
-                        warning.yellow()
+                        warning.to_string().yellow()
                     );
                 }
             }
@@ -1071,6 +2009,64 @@ impl ArtifactReport {
             );
         }
         println!("{}", output);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+/// Combined results of checking multiple artifacts
+#[derive(Debug, Default)]
+pub struct CheckSummary {
+    ok: usize,
+    warnings: Vec<PackageIdent>,
+    errors: Vec<PackageIdent>,
+    failures: Vec<(String, anyhow::Error)>,
+}
+
+impl CheckSummary {
+    pub fn add(&mut self, report: &ArtifactReport) {
+        match report.status() {
+            ReportStatus::Error => self.errors.push(report.ident.clone()),
+            ReportStatus::Warning => self.warnings.push(report.ident.clone()),
+            ReportStatus::Ok => self.ok += 1,
+        }
+    }
+
+    pub fn add_failure(&mut self, package: impl Into<String>, err: anyhow::Error) {
+        self.failures.push((package.into(), err));
+    }
+
+    pub fn status(&self) -> ReportStatus {
+        if !self.errors.is_empty() || !self.failures.is_empty() {
+            return ReportStatus::Error;
+        }
+        if !self.warnings.is_empty() {
+            return ReportStatus::Warning;
+        }
+        ReportStatus::Ok
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Checked {} packages: {} ok, {} with warnings, {} with errors, {} failed - {}",
+            self.ok + self.warnings.len() + self.errors.len() + self.failures.len(),
+            self.ok,
+            self.warnings.len(),
+            self.errors.len(),
+            self.failures.len(),
+            self.status()
+        );
+        for ident in self.errors.iter() {
+            println!("{}: {}", ident, ReportStatus::Error);
+        }
+        for (package, err) in self.failures.iter() {
+            println!("{}: {}", package, format!("{:#}", err).red());
+        }
     }
 }
 
@@ -1090,13 +2086,21 @@ impl std::fmt::Display for ReportStatus {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct PackageReport {
-    errors: Vec<String>,
-    warnings: Vec<String>,
+    errors: Vec<Finding>,
+    warnings: Vec<Finding>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<LicenseEvidence>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    libraries: Vec<FileLibraries>,
 }
 
 impl PackageReport {
+    fn with_severity(mut self, check: &Check) -> PackageReport {
+        check.apply_severity(&mut self.errors, &mut self.warnings);
+        self
+    }
     pub fn status(&self) -> ReportStatus {
         if !self.errors.is_empty() {
             return ReportStatus::Error;
@@ -1108,13 +2112,17 @@ impl PackageReport {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct DirReport {
-    errors: Vec<String>,
-    warnings: Vec<String>,
+    errors: Vec<Finding>,
+    warnings: Vec<Finding>,
 }
 
 impl DirReport {
+    fn with_severity(mut self, check: &Check) -> DirReport {
+        check.apply_severity(&mut self.errors, &mut self.warnings);
+        self
+    }
     pub fn status(&self) -> ReportStatus {
         if !self.errors.is_empty() {
             return ReportStatus::Error;
@@ -1126,13 +2134,17 @@ impl DirReport {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct FileReport {
-    errors: Vec<String>,
-    warnings: Vec<String>,
+    errors: Vec<Finding>,
+    warnings: Vec<Finding>,
 }
 
 impl FileReport {
+    fn with_severity(mut self, check: &Check) -> FileReport {
+        check.apply_severity(&mut self.errors, &mut self.warnings);
+        self
+    }
     pub fn status(&self) -> ReportStatus {
         if !self.errors.is_empty() {
             return ReportStatus::Error;
@@ -1150,30 +2162,30 @@ pub struct EmptyTopLevelDirCheck {
 }
 
 impl EmptyTopLevelDirCheck {
-    fn id(&self) -> &'static str {
-        "EMPTY_TOP_LEVEL_DIR"
-    }
-    fn description(&self) -> &'static str {
-        "Checks if an installed package directory is empty"
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "EMPTY_TOP_LEVEL_DIR",
+        description: "Checks if an installed package directory is empty",
+        default_severity: CheckSeverity::Warning,
+        rules: &["EMPTY_DIR"],
+    };
+}
+
+#[async_trait]
+impl ArtifactCheck for EmptyTopLevelDirCheck {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &EmptyTopLevelDirCheck::METADATA
     }
-    async fn visit_dir_start(
-        &mut self,
-        _abs_path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
+    async fn visit_dir_start(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<DirReport> {
         self.dir_entry_count = Some(0);
         Ok(DirReport::default())
     }
-    async fn visit_dir_end(
-        &mut self,
-        _path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
+    async fn visit_dir_end(&mut self, _path: &Path, rel_path: &Path) -> Result<DirReport> {
         let file_count = self.dir_entry_count.take();
-        if file_count.unwrap() == 0 && rel_path.as_ref().components().count() == 1 {
+        if file_count.unwrap() == 0 && rel_path.components().count() == 1 {
             Ok(DirReport {
-                warnings: vec![format!(
-                    "Top level directory is empty, considered removing it in your plan"
+                warnings: vec![Finding::new(
+                    "EMPTY_DIR",
+                    "Top level directory is empty, considered removing it in your plan",
                 )],
                 errors: vec![],
             })
@@ -1181,27 +2193,15 @@ impl EmptyTopLevelDirCheck {
             Ok(DirReport::default())
         }
     }
-    async fn visit_child_dir(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
+    async fn visit_child_dir(&mut self, _path: &Path, _rel_path: &Path) -> Result<DirReport> {
         self.dir_entry_count = self.dir_entry_count.map(|count| count + 1);
         Ok(DirReport::default())
     }
-    async fn visit_file(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
+    async fn visit_file(&mut self, _path: &Path, _rel_path: &Path) -> Result<FileReport> {
         self.dir_entry_count = self.dir_entry_count.map(|count| count + 1);
         Ok(FileReport::default())
     }
-    async fn visit_symlink(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
+    async fn visit_symlink(&mut self, _path: &Path, _rel_path: &Path) -> Result<FileReport> {
         self.dir_entry_count = self.dir_entry_count.map(|count| count + 1);
         Ok(FileReport::default())
     }
@@ -1213,339 +2213,605 @@ impl EmptyTopLevelDirCheck {
 pub struct DependencyCheck<'a> {
     file_type_checker: Infer,
     package_metadata: &'a PackageMetadata,
+    pkgs_root: PathBuf,
+    install_dir: PathBuf,
     unused_deps: HashSet<PackageIdent>,
+    library_resolver: LibraryResolver,
+    library_paths: Option<Vec<PathBuf>>,
+    libraries: Vec<FileLibraries>,
+    undeclared_deps: BTreeSet<String>,
+    platform_shells: Vec<PathBuf>,
+    runtime_bin_paths: Option<Vec<PathBuf>>,
+}
+
+/// The shared libraries loaded for an ELF object in the package
+#[derive(Debug, Serialize)]
+pub struct FileLibraries {
+    path: PathBuf,
+    libraries: Vec<ResolvedLibrary>,
 }
 
 impl<'a> DependencyCheck<'a> {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "DEPENDENCY_CHECK",
+        description:
+            "Checks all dynamic linker dependencies, script interpreters and runtime dependencies",
+        default_severity: CheckSeverity::Error,
+        rules: &[
+            "INVALID_ELF",
+            "ELF_INTERPRETER",
+            "RPATH",
+            "RUNPATH",
+            "MISSING_LIBRARY",
+            "MISSING_VERSION",
+            "INVALID_SHEBANG",
+            "ENV_SPLIT",
+            "INTERPRETER",
+            "HOST_INTERPRETER",
+            "UNDECLARED_DEP",
+            "UNUSED_DEPS",
+            "NOT_ELF",
+            "BROKEN_SYMLINK",
+            "UNREADABLE",
+        ],
+    };
+
     fn script_matcher(buf: &[u8]) -> bool {
         return buf.len() >= 2 && buf[0] == 0x23 && buf[1] == 0x21;
     }
-    pub fn new(package_metadata: &'a PackageMetadata) -> DependencyCheck<'a> {
+    fn elf_matcher(buf: &[u8]) -> bool {
+        buf.starts_with(b"\x7fELF")
+    }
+    pub fn new(
+        package_metadata: &'a PackageMetadata,
+        pkgs_root: impl AsRef<Path>,
+        install_dir: impl AsRef<Path>,
+        platform_shells: Vec<PathBuf>,
+    ) -> DependencyCheck<'a> {
         let mut file_type_checker = infer::Infer::new();
         file_type_checker.add("script", "", DependencyCheck::script_matcher);
+        // Matches all ELF objects, including small ones that infer does not recognize
+        file_type_checker.add("elf", "", DependencyCheck::elf_matcher);
         DependencyCheck {
             file_type_checker,
             unused_deps: package_metadata.deps.clone(),
             package_metadata,
+            library_resolver: LibraryResolver::new(pkgs_root.as_ref()),
+            library_paths: None,
+            undeclared_deps: BTreeSet::new(),
+            libraries: Vec::new(),
+            platform_shells,
+            runtime_bin_paths: None,
+            pkgs_root: pkgs_root.as_ref().to_path_buf(),
+            install_dir: install_dir.as_ref().to_path_buf(),
         }
     }
-}
 
-impl<'a> DependencyCheck<'a> {
-    fn id(&self) -> &'static str {
-        "DEPENDENCY_CHECK"
-    }
-    fn description(&self) -> &'static str {
-        "Checks all dynamic linker dependencies, script interpreters and runtime dependencies"
-    }
-    async fn visit_dir_start(
-        &mut self,
-        _abs_path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
-        Ok(DirReport::default())
+    /// Directories searched for the libraries of shared objects, which have no interpreter to
+    /// tell where the default library directory is. These are the package's own library
+    /// directories followed by the directory of the dynamic linker in the runtime deps.
+    async fn library_paths(&mut self) -> Result<Vec<PathBuf>> {
+        if let Some(library_paths) = self.library_paths.as_ref() {
+            return Ok(library_paths.clone());
+        }
+        let mut library_paths = Vec::new();
+        for lib_dir in ["lib", "lib64"] {
+            let path = self.install_dir.join(lib_dir);
+            if resolve_in_root(self.pkgs_root.as_path(), &path).is_dir() {
+                library_paths.push(path);
+            }
+        }
+        for dep in self.package_metadata.deps.iter() {
+            for lib_dir in ["lib", "lib64"] {
+                let path = HAB_PKGS_PATH.join(PathBuf::from(dep)).join(lib_dir);
+                let Ok(mut entries) =
+                    tokio::fs::read_dir(resolve_in_root(self.pkgs_root.as_path(), &path)).await
+                else {
+                    continue;
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    let file_name = entry.file_name();
+                    let file_name = file_name.to_string_lossy();
+                    if file_name.starts_with("ld-linux") || file_name.starts_with("ld-musl") {
+                        library_paths.push(path);
+                        break;
+                    }
+                }
+            }
+        }
+        debug!(
+            "Library search paths for shared objects: {:?}",
+            library_paths
+        );
+        self.library_paths = Some(library_paths.clone());
+        Ok(library_paths)
     }
-    async fn visit_dir_end(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
-        Ok(DirReport::default())
+
+    /// Directories that `env` searches for a script's interpreter, these are the `PATH`
+    /// entries of the package followed by those of its direct and transitive runtime
+    /// dependencies. Packages without a `PATH` metafile are searched in their `bin` dir.
+    async fn runtime_bin_paths(&mut self) -> Result<Vec<PathBuf>> {
+        if let Some(bin_paths) = self.runtime_bin_paths.as_ref() {
+            return Ok(bin_paths.clone());
+        }
+        let mut deps = self
+            .package_metadata
+            .deps
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        deps.sort_by_key(|dep| dep.to_string());
+        let tdeps = tokio::fs::read_to_string(resolve_in_root(
+            self.pkgs_root.as_path(),
+            self.install_dir.join("TDEPS"),
+        ))
+        .await
+        .map(|data| {
+            data.lines()
+                .filter_map(|dep| PackageIdent::try_from(dep).ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+        let mut package_dirs = vec![self.install_dir.clone()];
+        for dep in deps.iter().chain(tdeps.iter()) {
+            let dep_dir = HAB_PKGS_PATH.join(PathBuf::from(dep));
+            if !package_dirs.contains(&dep_dir) {
+                package_dirs.push(dep_dir);
+            }
+        }
+        let mut bin_paths = Vec::new();
+        for package_dir in package_dirs {
+            let paths = match tokio::fs::read_to_string(resolve_in_root(
+                self.pkgs_root.as_path(),
+                package_dir.join("PATH"),
+            ))
+            .await
+            {
+                Ok(data) => data
+                    .trim()
+                    .split(':')
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect::<Vec<_>>(),
+                Err(_) => vec![package_dir.join("bin")],
+            };
+            for path in paths {
+                if !bin_paths.contains(&path) {
+                    bin_paths.push(path);
+                }
+            }
+        }
+        debug!("Search paths for script interpreters: {:?}", bin_paths);
+        self.runtime_bin_paths = Some(bin_paths.clone());
+        Ok(bin_paths)
     }
-    async fn visit_child_dir(
-        &mut self,
-        _path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<DirReport> {
-        Ok(DirReport::default())
+
+    /// Checks that the interpreter in the shebang of a script is available at runtime.
+    /// For `env` shebangs the program that `env` runs is checked instead, the host's
+    /// `env` is allowed as it only looks up the interpreter.
+    async fn check_script(&mut self, path: &Path) -> Result<FileReport> {
+        let mut shebang = String::new();
+        let file = File::open(path).await?;
+        let mut reader = BufReader::new(file);
+        if let Err(err) = reader.read_line(&mut shebang).await {
+            return Ok(FileReport {
+                warnings: vec![Finding::new(
+                    "INVALID_SHEBANG",
+                    format!("File starts with '#!' but has no interpreter: {}", err),
+                )],
+                errors: vec![],
+            });
+        }
+        let mut args = shebang
+            .strip_prefix("#!")
+            .unwrap_or_default()
+            .split_whitespace();
+        let Some(interpreter) = args.next() else {
+            return Ok(FileReport {
+                warnings: vec![],
+                errors: vec![Finding::new(
+                    "INVALID_SHEBANG",
+                    "Script interpreter not specified after shebang '#!'",
+                )],
+            });
+        };
+        let interpreter = PathBuf::from(interpreter);
+        let is_env = interpreter.file_name().is_some_and(|name| name == "env");
+        if !is_env {
+            return Ok(self.check_interpreter(&interpreter));
+        }
+        let mut report = if interpreter.starts_with(HAB_PKGS_PATH.as_path()) {
+            self.check_interpreter(&interpreter)
+        } else {
+            FileReport::default()
+        };
+        let args = args.collect::<Vec<_>>();
+        let (program, split) = env_program(&args);
+        if !split && args.len() > 1 {
+            report.warnings.push(Finding::new("ENV_SPLIT", format!(
+                "Script passes '{}' to env as a single argument, use 'env -S' to pass arguments to the interpreter",
+                args.join(" ")
+            )));
+        }
+        match program {
+            Some(program) if program.contains('/') => {
+                let program_report = self.check_interpreter(Path::new(program));
+                report.errors.extend(program_report.errors);
+                report.warnings.extend(program_report.warnings);
+            }
+            Some(program) => {
+                let bin_paths = self.runtime_bin_paths().await?;
+                let program_path = bin_paths
+                    .iter()
+                    .map(|bin_path| bin_path.join(program))
+                    .find(|program_path| {
+                        resolve_in_root(self.pkgs_root.as_path(), program_path).is_file()
+                    });
+                match program_path {
+                    Some(program_path) => match package_ident(program_path.as_path()) {
+                        Some(package)
+                            if self
+                                .package_metadata
+                                .all_runtime_deps()
+                                .any(|dep| dep == &package) =>
+                        {
+                            self.unused_deps.remove(&package);
+                        }
+                        Some(_) => self.report_undeclared_dep(
+                            &mut report,
+                            "Script interpreter",
+                            program_path.as_path(),
+                        ),
+                        None => {}
+                    },
+                    None => self.report_issue(
+                        &mut report,
+                        Finding::new(
+                            "INTERPRETER",
+                            format!(
+                                "Script interpreter {} is not provided by any runtime dependency",
+                                program
+                            ),
+                        ),
+                    ),
+                }
+            }
+            None => report.errors.push(Finding::new(
+                "INVALID_SHEBANG",
+                format!(
+                    "Script interpreter not specified after '{}'",
+                    interpreter.display()
+                ),
+            )),
+        }
+        Ok(report)
     }
-    async fn visit_file(
-        &mut self,
-        path: impl AsRef<Path>,
-        rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
-        match self.file_type_checker.get_from_path(path.as_ref()) {
-            Ok(file_type) => {
-                if let Some(file_type) = file_type {
-                    let mime_type = file_type.mime_type();
-                    if mime_type == "application/x-executable" {
-                        debug!("Checking libraries for {}", rel_path.as_ref().display());
-                        let buffer = tokio::fs::read(path.as_ref()).await?;
-                        let object = Object::parse(&buffer)?;
-                        match object {
-                            Object::Elf(elf) => {
-                                let mut report = FileReport::default();
-                                let interpreter_search_path = if let Some(interpreter) =
-                                    elf.interpreter
-                                {
-                                    let interpreter_path = PathBuf::from(interpreter);
-                                    if let Ok(interpreter_path) =
-                                        interpreter_path.strip_prefix(HAB_PKGS_PATH.as_path())
-                                    {
-                                        let _interpreter_package =
-                                            self.package_metadata.all_runtime_deps().find(|dep| {
-                                                if interpreter_path.starts_with(PathBuf::from(*dep))
-                                                {
-                                                    self.unused_deps.remove(dep);
-                                                    true
-                                                } else {
-                                                    false
-                                                }
-                                            });
-                                    } else {
-                                        match self.package_metadata.pkg_type {
-                                            PackageType::Standard => {
-                                                report.errors.push(format!("Executable's ELF interpreter does not belong to a hab package: {}",interpreter))
-                                            },
-                                            PackageType::Native => {
-                                                report.warnings.push(format!("Executable's ELF interpreter does not belong to a hab package: {}",interpreter))
-                                            },
-                                        }
-                                    }
-                                    interpreter_path.parent().map(|p| p.to_path_buf())
-                                } else {
-                                    None
-                                };
-                                let rpaths = elf
-                                    .rpaths
-                                    .iter()
-                                    .flat_map(|v| v.split(':'))
-                                    .map(|v| {
-                                        if v.contains("$ORIGIN") {
-                                            PathBuf::from(v.replace(
-                                                "$ORIGIN",
-                                                path.as_ref().parent().unwrap().to_str().unwrap(),
-                                            ))
-                                        } else {
-                                            PathBuf::from(v)
-                                        }
+
+    /// Checks an interpreter given by its absolute path
+    fn check_interpreter(&mut self, interpreter: &Path) -> FileReport {
+        if self
+            .platform_shells
+            .iter()
+            .any(|shell| shell == interpreter)
+        {
+            FileReport::default()
+        } else if let Ok(interpreter_path) = interpreter.strip_prefix(HAB_PKGS_PATH.as_path()) {
+            let interpreter_package = self.package_metadata.all_runtime_deps().find(|dep| {
+                if interpreter_path.starts_with(PathBuf::from(*dep)) {
+                    self.unused_deps.remove(dep);
+                    true
+                } else {
+                    false
+                }
+            });
+
+            if interpreter_package.is_some() {
+                // Check that interpreter exists
+                if resolve_in_root(self.pkgs_root.as_path(), interpreter).is_file() {
+                    FileReport::default()
+                } else {
+                    FileReport {
+                        warnings: vec![],
+                        errors: vec![Finding::new(
+                            "INTERPRETER",
+                            format!(
+                                "Script interpreter does not exist: {}",
+                                interpreter.display()
+                            ),
+                        )],
+                    }
+                }
+            } else {
+                let mut report = FileReport::default();
+                self.report_undeclared_dep(&mut report, "Script interpreter", interpreter);
+                report
+            }
+        } else if interpreter.is_relative() {
+            FileReport {
+                warnings: vec![Finding::new(
+                    "INTERPRETER",
+                    format!(
+                        "Script uses relative interpreter: {}",
+                        interpreter.display()
+                    ),
+                )],
+                errors: vec![],
+            }
+        } else {
+            let mut report = FileReport::default();
+            self.report_issue(
+                &mut report,
+                Finding::new(
+                    "HOST_INTERPRETER",
+                    format!(
+                        "Script uses interpreter on host system: {}",
+                        interpreter.display()
+                    ),
+                ),
+            );
+            report
+        }
+    }
+
+    /// Reports files that are named like shared libraries but are not ELF objects,
+    /// linker scripts such as glibc's `libc.so` are expected to be named this way.
+    async fn check_not_elf(&self, path: &Path, rel_path: &Path) -> Result<FileReport> {
+        let file_name = rel_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // Data files such as gdb auto-load scripts are named after the libraries they belong to
+        if rel_path.starts_with("share") || !is_shared_library_name(&file_name) {
+            return Ok(FileReport::default());
+        }
+        let data = tokio::fs::read(path).await?;
+        let is_linker_script = std::str::from_utf8(&data)
+            .map(|text| text.contains("INPUT") || text.contains("GROUP"))
+            .unwrap_or(false);
+        if is_linker_script {
+            return Ok(FileReport::default());
+        }
+        Ok(FileReport {
+            warnings: vec![Finding::new(
+                "NOT_ELF",
+                "File is named like a shared library but is not an ELF object or linker script",
+            )],
+            errors: vec![],
+        })
+    }
+
+    /// Reports a file that uses a package which is not a direct runtime dependency, such as a
+    /// package that is only a build dependency or only in the transitive runtime closure.
+    fn report_undeclared_dep(&mut self, report: &mut FileReport, usage: &str, path: &Path) {
+        let Some(package) = package_ident(path) else {
+            return;
+        };
+        let dep = format!("{}/{}", package.origin, package.name);
+        let reason = if self.package_metadata.build_deps.contains(&package) {
+            "is only a build dependency"
+        } else {
+            "is not a direct runtime dependency"
+        };
+        self.report_issue(
+            report,
+            Finding::new(
+                "UNDECLARED_DEP",
+                format!(
+                    "{} {} is provided by {} which {}, add '{}' to pkg_deps",
+                    usage,
+                    path.display(),
+                    package,
+                    reason,
+                    dep
+                ),
+            ),
+        );
+        self.undeclared_deps.insert(dep);
+    }
+
+    /// Native packages may depend on the host system so their issues are only warnings
+    fn report_issue(&self, report: &mut FileReport, issue: Finding) {
+        match self.package_metadata.pkg_type {
+            PackageType::Standard => report.errors.push(issue),
+            PackageType::Native => report.warnings.push(issue),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> ArtifactCheck for DependencyCheck<'a> {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &DependencyCheck::METADATA
+    }
+    async fn visit_dir_start(&mut self, _abs_path: &Path, _rel_path: &Path) -> Result<DirReport> {
+        Ok(DirReport::default())
+    }
+    async fn visit_dir_end(&mut self, _path: &Path, _rel_path: &Path) -> Result<DirReport> {
+        Ok(DirReport::default())
+    }
+    async fn visit_child_dir(&mut self, _path: &Path, _rel_path: &Path) -> Result<DirReport> {
+        Ok(DirReport::default())
+    }
+    async fn visit_file(&mut self, path: &Path, rel_path: &Path) -> Result<FileReport> {
+        match self.file_type_checker.get_from_path(path) {
+            Ok(file_type) => {
+                if let Some(file_type) = file_type {
+                    let mime_type = file_type.mime_type();
+                    if mime_type == "elf" {
+                        debug!("Checking libraries for {}", rel_path.display());
+                        let buffer = tokio::fs::read(path).await?;
+                        let object =
+                            match ElfObject::parse(&buffer, self.install_dir.join(rel_path)) {
+                                Ok(Some(object)) => Arc::new(object),
+                                Ok(None) => return Ok(FileReport::default()),
+                                Err(err) => {
+                                    return Ok(FileReport {
+                                        warnings: vec![],
+                                        errors: vec![Finding::new(
+                                            "INVALID_ELF",
+                                            format!(
+                                        "File has an ELF header but is not a valid ELF object: {}",
+                                        err
+                                    ),
+                                        )],
                                     })
-                                    .collect::<Vec<_>>();
-                                let runpaths = elf
-                                    .runpaths
-                                    .iter()
-                                    .flat_map(|v| v.split(':'))
-                                    .map(|v| {
-                                        if v.contains("$ORIGIN") {
-                                            PathBuf::from(v.replace(
-                                                "$ORIGIN",
-                                                path.as_ref().parent().unwrap().to_str().unwrap(),
-                                            ))
+                                }
+                            };
+                        let mut report = FileReport::default();
+                        if let Some(interpreter) = object.interpreter.as_ref() {
+                            if let Ok(interpreter_path) =
+                                interpreter.strip_prefix(HAB_PKGS_PATH.as_path())
+                            {
+                                let interpreter_package =
+                                    self.package_metadata.all_runtime_deps().find(|dep| {
+                                        if interpreter_path.starts_with(PathBuf::from(*dep)) {
+                                            self.unused_deps.remove(dep);
+                                            true
                                         } else {
-                                            PathBuf::from(v)
+                                            false
                                         }
-                                    })
-                                    .collect::<Vec<_>>();
-                                for rpath in rpaths.iter() {
-                                    if !rpath.starts_with(HAB_PKGS_PATH.as_path()) {
-                                        match self.package_metadata.pkg_type {
-                                            PackageType::Standard => {
-                                                report.errors.push(format!(
-                                            "RPATH directory '{}' does not belong to a hab package",
-                                            rpath.display()
-                                        ));
-                                            }
-                                            PackageType::Native => {
-                                                report.warnings.push(format!(
+                                    });
+                                if interpreter_package.is_none() {
+                                    self.report_undeclared_dep(
+                                        &mut report,
+                                        "ELF interpreter",
+                                        interpreter,
+                                    );
+                                }
+                            } else {
+                                self.report_issue(
+                                    &mut report,
+                                    Finding::new("ELF_INTERPRETER", format!(
+                                        "Executable's ELF interpreter does not belong to a hab package: {}",
+                                        interpreter.display()
+                                    ),
+                                ));
+                            }
+                        }
+                        for rpath in object.rpaths.iter() {
+                            if !rpath.starts_with(HAB_PKGS_PATH.as_path()) {
+                                self.report_issue(
+                                    &mut report,
+                                    Finding::new(
+                                        "RPATH",
+                                        format!(
                                             "RPATH directory '{}' does not belong to a hab package",
                                             rpath.display()
-                                        ));
-                                            }
-                                        }
-                                    }
-                                }
-                                for runpath in runpaths.iter() {
-                                    if !runpath.starts_with(HAB_PKGS_PATH.as_path()) {
-                                        match self.package_metadata.pkg_type {
-                                            PackageType::Standard => {
-                                                report.errors.push(format!(
-                                            "RUNPATH directory '{}' does not belong to a hab package",
-                                            runpath.display()
-                                        ));
-                                            }
-                                            PackageType::Native => {
-                                                report.warnings.push(format!(
-                                            "RUNPATH directory '{}' does not belong to a hab package",
-                                            runpath.display()
-                                        ));
-                                            }
-                                        }
-                                    }
-                                }
-                                for library in elf.libraries.iter() {
-                                    let mut found = false;
-                                    for search_path in rpaths
-                                        .iter()
-                                        .chain(runpaths.iter())
-                                        .chain(interpreter_search_path.iter())
-                                    {
-                                        let library_path = search_path.join(library);
-                                        if library_path.is_file() {
-                                            found = true;
-                                            trace!(
-                                                "For {} library {} found in {}",
-                                                rel_path.as_ref().display(),
-                                                library,
-                                                library_path.display()
-                                            );
-                                            self.package_metadata.all_runtime_deps().find(|dep| {
-                                                if library_path
-                                                    .strip_prefix(HAB_PKGS_PATH.as_path())
-                                                    .ok()
-                                                    .map(|p| p.starts_with(PathBuf::from(*dep)))
-                                                    .unwrap_or_default()
-                                                {
-                                                    self.unused_deps.remove(dep);
-                                                    true
-                                                } else {
-                                                    false
-                                                }
-                                            });
-                                            break;
-                                        }
-                                    }
-                                    if !found {
-                                        match self.package_metadata.pkg_type {
-                                            PackageType::Standard => {
-                                                report.errors.push(format!(
-                                            "Library {} not found in any RPATH or RUNPATH directory: {:?}",
-                                            library,
-                                            rpaths
-                                                .iter()
-                                                .chain(runpaths.iter())
-                                                .chain(interpreter_search_path.iter())
-                                                .collect::<Vec<_>>(),
-                                        ));
-                                            }
-                                            PackageType::Native => {
-                                                report.warnings.push(format!(
-                                            "Library {} not found in any RPATH or RUNPATH directory: {:?}",
-                                            library,
-                                            rpaths
-                                                .iter()
-                                                .chain(runpaths.iter())
-                                                .chain(interpreter_search_path.iter())
-                                                .collect::<Vec<_>>(),
-                                        ));
-                                            }
-                                        }
-                                    }
-                                }
-                                Ok(report)
+                                        ),
+                                    ),
+                                );
                             }
-                            _ => Ok(FileReport::default()),
                         }
-                    } else if mime_type == "script" {
-                        let mut interpreter = String::new();
-                        let file = File::open(path.as_ref()).await?;
-                        let mut reader = BufReader::new(file);
-                        match reader.read_line(&mut interpreter).await {
-                            Ok(_) => {
-                                let interpreter = interpreter.strip_prefix("#!").unwrap();
-                                if let Some(interpreter) = interpreter.split_whitespace().next() {
-                                    let interpreter = PathBuf::from(interpreter);
-                                    if PLATFORM_SHELLS.contains(&interpreter) {
-                                        Ok(FileReport::default())
-                                    } else if let Ok(interpreter_path) =
-                                        interpreter.strip_prefix(HAB_PKGS_PATH.as_path())
+                        for runpath in object.runpaths.iter() {
+                            if !runpath.starts_with(HAB_PKGS_PATH.as_path()) {
+                                self.report_issue(
+                                    &mut report,
+                                    Finding::new(
+                                        "RUNPATH",
+                                        format!(
+                                        "RUNPATH directory '{}' does not belong to a hab package",
+                                        runpath.display()
+                                    ),
+                                    ),
+                                );
+                            }
+                        }
+                        let library_paths = if object.interpreter.is_none() {
+                            self.library_paths().await?
+                        } else {
+                            vec![]
+                        };
+                        let resolution = self
+                            .library_resolver
+                            .resolve(object.clone(), &library_paths)
+                            .await;
+                        for library in resolution.libraries.iter() {
+                            if library.needed_by != object.path {
+                                continue;
+                            }
+                            let library_package =
+                                self.package_metadata.all_runtime_deps().find(|dep| {
+                                    if library
+                                        .path
+                                        .strip_prefix(HAB_PKGS_PATH.as_path())
+                                        .ok()
+                                        .map(|p| p.starts_with(PathBuf::from(*dep)))
+                                        .unwrap_or_default()
                                     {
-                                        let interpreter_package =
-                                            self.package_metadata.all_runtime_deps().find(|dep| {
-                                                if interpreter_path.starts_with(PathBuf::from(*dep))
-                                                {
-                                                    self.unused_deps.remove(dep);
-                                                    true
-                                                } else {
-                                                    false
-                                                }
-                                            });
-
-                                        if let Some(_) = interpreter_package {
-                                            // Check that interpreter exists
-                                            if interpreter.is_file() {
-                                                Ok(FileReport::default())
-                                            } else {
-                                                Ok(FileReport {
-                                                    warnings: vec![],
-                                                    errors: vec![format!(
-                                                        "Script interpreter does not exist: {}",
-                                                        interpreter.display()
-                                                    )],
-                                                })
-                                            }
-                                        } else {
-                                            Ok(FileReport {
-                                        warnings: vec![],
-                                        errors: vec![format!(
-                                            "Script interpreter's package is not a runtime dependency: {}",
-                                            interpreter_path.components().take(4).collect::<PathBuf>().display()
-                                        )],
-                                    })
-                                        }
-                                    } else if interpreter.is_relative() {
-                                        Ok(FileReport {
-                                            warnings: vec![format!(
-                                                "Script uses relative interpreter: {}",
-                                                interpreter.display()
-                                            )],
-                                            errors: vec![],
-                                        })
+                                        self.unused_deps.remove(dep);
+                                        true
                                     } else {
-                                        match self.package_metadata.pkg_type {
-                                            PackageType::Standard => Ok(FileReport {
-                                                warnings: vec![],
-                                                errors: vec![format!(
-                                                    "Script uses interpreter on host system: {}",
-                                                    interpreter.display()
-                                                )],
-                                            }),
-                                            PackageType::Native => Ok(FileReport {
-                                                warnings: vec![format!(
-                                                    "Script uses interpreter on host system: {}",
-                                                    interpreter.display()
-                                                )],
-                                                errors: vec![],
-                                            }),
-                                        }
+                                        false
                                     }
-                                } else {
-                                    Ok(FileReport {
-                                        warnings: vec![],
-                                        errors: vec![format!(
-                                            "Script interpreter not specified after shebang '#!'",
-                                        )],
-                                    })
-                                }
+                                });
+                            if library_package.is_none() {
+                                self.report_undeclared_dep(
+                                    &mut report,
+                                    &format!("Library {}", library.name),
+                                    &library.path,
+                                );
                             }
-                            Err(err) => Ok(FileReport {
-                                warnings: vec![format!(
-                                    "File starts with '#!' but has no interpreter: {}",
-                                    err
-                                )],
-                                errors: vec![],
-                            }),
                         }
+                        for library in resolution.missing_libraries.iter() {
+                            if library.needed_by == object.path {
+                                self.report_issue(
+                                    &mut report,
+                                    Finding::new("MISSING_LIBRARY", format!(
+                                        "Library {} not found in any RPATH or RUNPATH directory: {:?}",
+                                        library.name, library.search_paths
+                                    ),
+                                ));
+                            } else {
+                                self.report_issue(
+                                    &mut report,
+                                    Finding::new("MISSING_LIBRARY", format!(
+                                        "Library {} needed by {} not found in any RPATH or RUNPATH directory: {:?}",
+                                        library.name,
+                                        library.needed_by.display(),
+                                        library.search_paths
+                                    ),
+                                ));
+                            }
+                        }
+                        for version in resolution.missing_versions.iter() {
+                            self.report_issue(
+                                &mut report,
+                                Finding::new(
+                                    "MISSING_VERSION",
+                                    format!(
+                                        "Version {} required by {} is not defined by {}",
+                                        version.version,
+                                        version.needed_by.display(),
+                                        version.library.display()
+                                    ),
+                                ),
+                            );
+                        }
+                        self.libraries.push(FileLibraries {
+                            path: rel_path.to_path_buf(),
+                            libraries: resolution.libraries,
+                        });
+                        Ok(report)
+                    } else if mime_type == "script" {
+                        self.check_script(path).await
                     } else {
                         trace!(
                             "File {} has type {}",
-                            rel_path.as_ref().display(),
+                            rel_path.display(),
                             file_type.mime_type()
                         );
-                        Ok(FileReport::default())
+                        self.check_not_elf(path, rel_path).await
                     }
                 } else {
-                    trace!(
-                        "Failed to determine file type of {}",
-                        rel_path.as_ref().display(),
-                    );
-                    Ok(FileReport::default())
+                    trace!("Failed to determine file type of {}", rel_path.display(),);
+                    self.check_not_elf(path, rel_path).await
                 }
             }
             Err(err) => {
                 if err.kind() == ErrorKind::PermissionDenied {
                     Ok(FileReport {
                         errors: vec![],
-                        warnings: vec![format!("File could not be verified due to insufficient permissions, try re-running check as root")]
+                        warnings: vec![Finding::new("UNREADABLE", "File could not be verified due to insufficient permissions, try re-running check as root")]
                     })
                 } else {
                     Err(err.into())
@@ -1553,35 +2819,1332 @@ impl<'a> DependencyCheck<'a> {
             }
         }
     }
-    async fn visit_symlink(
-        &mut self,
-        path: impl AsRef<Path>,
-        _rel_path: impl AsRef<Path>,
-    ) -> Result<FileReport> {
-        if !path.as_ref().exists() {
+    async fn visit_symlink(&mut self, path: &Path, _rel_path: &Path) -> Result<FileReport> {
+        let target = tokio::fs::read_link(path).await?;
+        let target = if target.is_absolute() {
+            resolve_in_root(self.pkgs_root.as_path(), target)
+        } else {
+            path.parent().unwrap().join(target)
+        };
+        if !target.exists() {
             Ok(FileReport {
                 warnings: vec![],
-                errors: vec![format!("Broken symlink, points to non-existent file",)],
+                errors: vec![Finding::new(
+                    "BROKEN_SYMLINK",
+                    "Broken symlink, points to non-existent file",
+                )],
             })
         } else {
             Ok(FileReport::default())
         }
     }
     async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        let mut report = PackageReport {
+            libraries: std::mem::take(&mut self.libraries),
+            ..Default::default()
+        };
         if !self.unused_deps.is_empty() {
-            Ok(PackageReport {
-                errors: vec![],
-                warnings: vec![format!(
+            report.warnings.push(Finding::new(
+                "UNUSED_DEPS",
+                format!(
                     "Package does not seem to use the following runtime deps: {}",
                     self.unused_deps
                         .iter()
                         .map(|d| d.to_string())
                         .collect::<Vec<_>>()
                         .join(", ")
-                )],
+                ),
+            ));
+        }
+        if !self.undeclared_deps.is_empty() {
+            report.warnings.push(Finding::new("UNDECLARED_DEP", format!(
+                "Package uses packages that are not direct runtime deps, add them to pkg_deps: pkg_deps+=({})",
+                std::mem::take(&mut self.undeclared_deps)
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )));
+        }
+        Ok(report)
+    }
+}
+
+/// Whether a file is named like a shared library, either `lib*.so` or `*.so.<version>`
+/// with a version made of dot separated numbers such as `libfoo.so.1.2`.
+fn is_shared_library_name(file_name: &str) -> bool {
+    if file_name.starts_with("lib") && file_name.ends_with(".so") {
+        return true;
+    }
+    match file_name.split_once(".so.") {
+        Some((name, version)) => {
+            !name.is_empty()
+                && version
+                    .split('.')
+                    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        }
+        None => false,
+    }
+}
+
+/// Finds the program that `env` runs from its arguments in a shebang, skipping its options
+/// and variable assignments. Also returns whether the arguments were split with `env -S`,
+/// without it the kernel passes everything after `env` as a single argument.
+fn env_program<'a>(args: &[&'a str]) -> (Option<&'a str>, bool) {
+    let mut split = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-S" | "--split-string" => split = true,
+            "-u" | "--unset" | "-C" | "--chdir" => {
+                args.next();
+            }
+            _ => {
+                if let Some(program) = arg
+                    .strip_prefix("--split-string=")
+                    .or_else(|| arg.strip_prefix("-S"))
+                {
+                    split = true;
+                    if !program.is_empty() {
+                        return (Some(program), split);
+                    }
+                } else if !arg.starts_with('-') && !arg.contains('=') {
+                    return (Some(arg), split);
+                }
+            }
+        }
+    }
+    (None, split)
+}
+
+/// Prefixes of paths that only exist while a package is being built
+const BUILD_TIME_PATHS: &[&str] = &["/hab/studios/", "/hab/cache/src/", "/hab/cache/keys/"];
+/// Prefixes of host system paths that are not available to hab packages
+const HOST_SYSTEM_PATHS: &[&str] = &[
+    "/bin/",
+    "/sbin/",
+    "/lib/",
+    "/lib64/",
+    "/usr/",
+    "/opt/",
+    "/tmp/",
+    "/var/tmp/",
+];
+/// Documentation is not read by programs so its paths are not checked
+const DOCUMENTATION_DIRS: &[&str] = &["share/doc", "share/info", "share/man", "share/licenses"];
+/// Larger text files are usually data and are not checked
+const MAX_TEXT_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Number of paths listed in a single finding
+const MAX_REPORTED_PATHS: usize = 5;
+
+pub struct HardcodedPathCheck<'a> {
+    package_metadata: &'a PackageMetadata,
+    platform_shells: Vec<PathBuf>,
+}
+
+impl<'a> HardcodedPathCheck<'a> {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "HARDCODED_PATH",
+        description:
+            "Checks files for hard-coded build time, host system and build dependency paths",
+        default_severity: CheckSeverity::Error,
+        rules: &["DEP_PATH", "BUILD_TIME_PATH", "HOST_PATH"],
+    };
+
+    pub fn new(
+        package_metadata: &'a PackageMetadata,
+        platform_shells: Vec<PathBuf>,
+    ) -> HardcodedPathCheck<'a> {
+        HardcodedPathCheck {
+            package_metadata,
+            platform_shells,
+        }
+    }
+
+    fn report_paths(
+        &self,
+        report: &mut FileReport,
+        rule: &'static str,
+        description: &str,
+        paths: BTreeSet<String>,
+    ) {
+        if paths.is_empty() {
+            return;
+        }
+        let mut listed = paths
+            .iter()
+            .take(MAX_REPORTED_PATHS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if paths.len() > MAX_REPORTED_PATHS {
+            listed.push_str(&format!(" and {} more", paths.len() - MAX_REPORTED_PATHS));
+        }
+        let issue = Finding::new(rule, format!("File references {}: {}", description, listed));
+        match self.package_metadata.pkg_type {
+            PackageType::Standard => report.errors.push(issue),
+            PackageType::Native => report.warnings.push(issue),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> ArtifactCheck for HardcodedPathCheck<'a> {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &HardcodedPathCheck::METADATA
+    }
+    async fn visit_file(&mut self, path: &Path, rel_path: &Path) -> Result<FileReport> {
+        if DOCUMENTATION_DIRS
+            .iter()
+            .any(|dir| rel_path.starts_with(dir))
+            || tokio::fs::metadata(path).await?.len() > MAX_TEXT_FILE_SIZE
+        {
+            return Ok(FileReport::default());
+        }
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                return Ok(FileReport::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let strings = if data.starts_with(b"\x7fELF") {
+            match goblin::elf::Elf::parse(&data) {
+                Ok(elf) => elf_strings(&elf, &data),
+                Err(_) => return Ok(FileReport::default()),
+            }
+        } else if data.iter().take(8192).any(|b| *b == 0) {
+            return Ok(FileReport::default());
+        } else if data.starts_with(b"#!") {
+            // Script interpreters are verified by the dependency check
+            let start = data.iter().position(|b| *b == b'\n').unwrap_or(data.len());
+            vec![&data[start..]]
+        } else {
+            vec![data.as_slice()]
+        };
+
+        let mut build_time_paths = BTreeSet::new();
+        let mut host_paths = BTreeSet::new();
+        let mut dep_paths = BTreeSet::new();
+        for referenced_path in strings.into_iter().flat_map(absolute_paths) {
+            if referenced_path.starts_with(HAB_PKGS_PATH.to_str().unwrap()) {
+                if !is_runtime_path(self.package_metadata, &referenced_path) {
+                    dep_paths.insert(referenced_path);
+                }
+            } else if BUILD_TIME_PATHS
+                .iter()
+                .any(|prefix| referenced_path.starts_with(prefix))
+            {
+                build_time_paths.insert(referenced_path);
+            } else if HOST_SYSTEM_PATHS
+                .iter()
+                .any(|prefix| referenced_path.starts_with(prefix))
+                && !self
+                    .platform_shells
+                    .contains(&PathBuf::from(&referenced_path))
+            {
+                host_paths.insert(referenced_path);
+            }
+        }
+        let mut report = FileReport::default();
+        self.report_paths(
+            &mut report,
+            "DEP_PATH",
+            "packages that are not runtime dependencies",
+            dep_paths,
+        );
+        self.report_paths(
+            &mut report,
+            "BUILD_TIME_PATH",
+            "build time paths",
+            build_time_paths,
+        );
+        self.report_paths(&mut report, "HOST_PATH", "host system paths", host_paths);
+        Ok(report)
+    }
+    async fn visit_symlink(&mut self, _path: &Path, _rel_path: &Path) -> Result<FileReport> {
+        Ok(FileReport::default())
+    }
+    async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        Ok(PackageReport::default())
+    }
+}
+
+pub struct PkgConfigCheck<'a> {
+    package_metadata: &'a PackageMetadata,
+    pkgs_root: PathBuf,
+    install_dir: PathBuf,
+    modules: Option<HashSet<String>>,
+}
+
+impl<'a> PkgConfigCheck<'a> {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "PKG_CONFIG",
+        description:
+            "Checks pkg-config and libtool files refer to the package and its runtime deps",
+        default_severity: CheckSeverity::Error,
+        rules: &["VARIABLE_PATH", "REQUIRES", "FOREIGN_PATH", "LIBTOOL"],
+    };
+
+    pub fn new(
+        package_metadata: &'a PackageMetadata,
+        pkgs_root: impl AsRef<Path>,
+        install_dir: impl AsRef<Path>,
+    ) -> PkgConfigCheck<'a> {
+        PkgConfigCheck {
+            package_metadata,
+            pkgs_root: pkgs_root.as_ref().to_path_buf(),
+            install_dir: install_dir.as_ref().to_path_buf(),
+            modules: None,
+        }
+    }
+
+    /// The pkg-config modules available to users of the package, these are the modules in
+    /// the `PKG_CONFIG_PATH` of the package and of its runtime deps.
+    async fn modules(&mut self) -> Result<&HashSet<String>> {
+        if self.modules.is_none() {
+            let mut pkg_config_dirs = Vec::new();
+            for dep in self.package_metadata.all_runtime_deps() {
+                let metafile = resolve_in_root(
+                    self.pkgs_root.as_path(),
+                    HAB_PKGS_PATH
+                        .join(PathBuf::from(dep))
+                        .join("PKG_CONFIG_PATH"),
+                );
+                if let Ok(pkg_config_path) = tokio::fs::read_to_string(metafile).await {
+                    pkg_config_dirs.extend(
+                        pkg_config_path
+                            .trim()
+                            .split(':')
+                            .filter(|dir| !dir.is_empty())
+                            .map(PathBuf::from),
+                    );
+                }
+            }
+            let mut modules = HashSet::new();
+            for pkg_config_dir in pkg_config_dirs {
+                let Ok(mut entries) =
+                    tokio::fs::read_dir(resolve_in_root(self.pkgs_root.as_path(), &pkg_config_dir))
+                        .await
+                else {
+                    continue;
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    if let Some(module) = entry.file_name().to_string_lossy().strip_suffix(".pc") {
+                        modules.insert(module.to_string());
+                    }
+                }
+            }
+            debug!("Found {} pkg-config modules in runtime deps", modules.len());
+            self.modules = Some(modules);
+        }
+        Ok(self.modules.as_ref().unwrap())
+    }
+
+    async fn check_pkg_config_file(&mut self, data: &str) -> Result<FileReport> {
+        let mut report = FileReport::default();
+        let pkg_config = PkgConfigFile::parse(data);
+        for variable in ["prefix", "exec_prefix", "libdir", "includedir"] {
+            if let Some(value) = pkg_config.variables.get(variable) {
+                if value.starts_with('/') && !Path::new(value).starts_with(&self.install_dir) {
+                    report.errors.push(Finding::new(
+                        "VARIABLE_PATH",
+                        format!(
+                            "Variable '{}' points outside the package: {}",
+                            variable, value
+                        ),
+                    ));
+                }
+            }
+        }
+        for (field, is_private) in [("Requires", false), ("Requires.private", true)] {
+            let Some(requires) = pkg_config.fields.get(field) else {
+                continue;
+            };
+            let modules = self.modules().await?;
+            let missing_modules = pkg_config_modules(requires)
+                .into_iter()
+                .filter(|module| !modules.contains(module))
+                .collect::<Vec<_>>();
+            if missing_modules.is_empty() {
+                continue;
+            }
+            let issue = Finding::new(
+                "REQUIRES",
+                format!(
+                    "{} modules not found in the PKG_CONFIG_PATH of the package or its runtime deps: {}",
+                    field,
+                    missing_modules.join(", ")
+                ),
+            );
+            if is_private {
+                report.warnings.push(issue);
+            } else {
+                report.errors.push(issue);
+            }
+        }
+        for field in ["Libs", "Libs.private", "Cflags"] {
+            let Some(value) = pkg_config.fields.get(field) else {
+                continue;
+            };
+            let foreign_paths = absolute_paths(value.as_bytes())
+                .filter(|path| {
+                    !path.starts_with(HAB_PKGS_PATH.to_str().unwrap())
+                        || !is_runtime_path(self.package_metadata, path)
+                })
+                .collect::<BTreeSet<_>>();
+            if !foreign_paths.is_empty() {
+                report.errors.push(Finding::new(
+                    "FOREIGN_PATH",
+                    format!(
+                    "{} refers to paths that do not belong to the package or its runtime deps: {}",
+                    field,
+                    foreign_paths.into_iter().collect::<Vec<_>>().join(", ")
+                ),
+                ));
+            }
+        }
+        Ok(report)
+    }
+
+    fn check_libtool_file(&self, data: &str) -> FileReport {
+        let mut report = FileReport::default();
+        let stale_paths = data
+            .lines()
+            .filter(|line| line.starts_with("dependency_libs=") || line.starts_with("libdir="))
+            .flat_map(|line| absolute_paths(line.as_bytes()).collect::<Vec<_>>())
+            .filter(|path| {
+                path.starts_with(HAB_PKGS_PATH.to_str().unwrap())
+                    && !is_runtime_path(self.package_metadata, path)
             })
+            .collect::<BTreeSet<_>>();
+        if !stale_paths.is_empty() {
+            report.warnings.push(Finding::new("LIBTOOL", format!(
+                "Libtool archive refers to packages that are not runtime deps, consider removing .la files in your plan: {}",
+                stale_paths.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        report
+    }
+}
+
+#[async_trait]
+impl<'a> ArtifactCheck for PkgConfigCheck<'a> {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &PkgConfigCheck::METADATA
+    }
+    async fn visit_file(&mut self, path: &Path, rel_path: &Path) -> Result<FileReport> {
+        match rel_path.extension().and_then(|x| x.to_str()) {
+            Some("pc") => {
+                let data = tokio::fs::read(path).await?;
+                self.check_pkg_config_file(&String::from_utf8_lossy(&data))
+                    .await
+            }
+            Some("la") => {
+                let data = tokio::fs::read(path).await?;
+                Ok(self.check_libtool_file(&String::from_utf8_lossy(&data)))
+            }
+            _ => Ok(FileReport::default()),
+        }
+    }
+    async fn visit_symlink(&mut self, _path: &Path, _rel_path: &Path) -> Result<FileReport> {
+        Ok(FileReport::default())
+    }
+    async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        Ok(PackageReport::default())
+    }
+}
+
+/// Number of bytes read from an executable file to determine if it is a script
+const SCRIPT_SNIFF_SIZE: u64 = 512;
+
+pub struct PermissionsCheck<'a> {
+    entries: HashMap<&'a Path, &'a ArtifactEntry>,
+    /// The package's bin dirs relative to its installation directory
+    bin_dirs: Vec<PathBuf>,
+}
+
+impl<'a> PermissionsCheck<'a> {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "PERMISSIONS",
+        description:
+            "Checks for world-writable, setuid, setgid and non-executable programs and for files not owned by root",
+        default_severity: CheckSeverity::Error,
+        rules: &["WORLD_WRITABLE", "OWNER", "SETUID", "SETGID", "NOT_EXECUTABLE", "NO_SHEBANG"],
+    };
+
+    pub fn new(
+        package_metadata: &PackageMetadata,
+        contents: &'a PackageArtifactContents,
+        install_dir: impl AsRef<Path>,
+    ) -> PermissionsCheck<'a> {
+        PermissionsCheck {
+            entries: contents
+                .entries
+                .iter()
+                .map(|entry| (entry.path.as_path(), entry))
+                .collect(),
+            bin_dirs: package_metadata
+                .bin_dirs
+                .iter()
+                .filter_map(|bin_dir| bin_dir.strip_prefix(install_dir.as_ref()).ok())
+                .map(Path::to_path_buf)
+                .collect(),
+        }
+    }
+
+    /// Checks the mode and ownership that apply to both files and directories
+    fn check_entry(
+        &self,
+        kind: &str,
+        entry: &ArtifactEntry,
+        errors: &mut Vec<Finding>,
+        warnings: &mut Vec<Finding>,
+    ) {
+        if entry.mode & 0o002 != 0 {
+            errors.push(Finding::new(
+                "WORLD_WRITABLE",
+                format!(
+                    "{} is world-writable (mode {:04o})",
+                    kind,
+                    entry.mode & 0o7777
+                ),
+            ));
+        }
+        if entry.uid != 0 || entry.gid != 0 {
+            warnings.push(Finding::new(
+                "OWNER",
+                format!(
+                    "{} is owned by uid {} and gid {} instead of root",
+                    kind, entry.uid, entry.gid
+                ),
+            ));
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> ArtifactCheck for PermissionsCheck<'a> {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &PermissionsCheck::METADATA
+    }
+    async fn visit_child_dir(&mut self, _path: &Path, rel_path: &Path) -> Result<DirReport> {
+        let mut report = DirReport::default();
+        if let Some(entry) = self.entries.get(rel_path) {
+            self.check_entry("Directory", entry, &mut report.errors, &mut report.warnings);
+        }
+        Ok(report)
+    }
+    async fn visit_file(&mut self, path: &Path, rel_path: &Path) -> Result<FileReport> {
+        let mut report = FileReport::default();
+        let entry = match self.entries.get(rel_path) {
+            Some(entry) => *entry,
+            None => return Ok(report),
+        };
+        self.check_entry("File", entry, &mut report.errors, &mut report.warnings);
+        let mode = entry.mode & 0o7777;
+        if mode & 0o4000 != 0 {
+            report.errors.push(Finding::new(
+                "SETUID",
+                format!("File is setuid (mode {:04o})", mode),
+            ));
+        }
+        if mode & 0o2000 != 0 {
+            report.errors.push(Finding::new(
+                "SETGID",
+                format!("File is setgid (mode {:04o})", mode),
+            ));
+        }
+        let is_executable = mode & 0o111 != 0;
+        let in_bin_dir = rel_path
+            .parent()
+            .is_some_and(|parent| self.bin_dirs.iter().any(|bin_dir| bin_dir == parent));
+        if in_bin_dir && !is_executable {
+            report.errors.push(Finding::new(
+                "NOT_EXECUTABLE",
+                format!(
+                    "File in {} is not executable (mode {:04o})",
+                    rel_path.parent().unwrap().display(),
+                    mode
+                ),
+            ));
+        }
+        if is_executable {
+            let mut data = Vec::new();
+            File::open(path)
+                .await?
+                .take(SCRIPT_SNIFF_SIZE)
+                .read_to_end(&mut data)
+                .await?;
+            // Text that is cut off in the middle of a character is still text
+            let is_text = !data.contains(&0)
+                && std::str::from_utf8(&data)
+                    .map_or_else(|err| err.error_len().is_none(), |_| true);
+            if !data.is_empty()
+                && !data.starts_with(b"\x7fELF")
+                && !data.starts_with(b"#!")
+                && is_text
+            {
+                report.warnings.push(Finding::new("NO_SHEBANG", format!(
+                    "File is executable but is not an ELF object and has no shebang '#!' (mode {:04o})",
+                    mode
+                )));
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Metafiles that list fully qualified package identifiers, one per line
+const DEPENDENCY_METAFILES: &[&str] = &["DEPS", "TDEPS", "BUILD_DEPS", "BUILD_TDEPS"];
+/// Metafiles that hold a colon separated list of absolute paths
+const PATH_METAFILES: &[&str] = &["PATH", "LD_RUN_PATH"];
+
+pub struct MetafileCheck {
+    ident: PackageArtifactIdent,
+    root: PathBuf,
+    install_dir: PathBuf,
+}
+
+impl MetafileCheck {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "METAFILES",
+        description: "Checks that the package metafiles are well formed and consistent",
+        default_severity: CheckSeverity::Error,
+        rules: &[
+            "IDENT",
+            "TARGET",
+            "INVALID_DEP",
+            "DUPLICATE_DEP",
+            "SELF_DEP",
+            "TDEPS",
+            "SEARCH_PATH",
+            "ASSIGNMENT",
+            "SVC_USER",
+            "MANIFEST",
+        ],
+    };
+
+    pub fn new(
+        ident: PackageArtifactIdent,
+        root: impl AsRef<Path>,
+        install_dir: impl AsRef<Path>,
+    ) -> MetafileCheck {
+        MetafileCheck {
+            ident,
+            root: root.as_ref().to_path_buf(),
+            install_dir: install_dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Reads a metafile of the package or of an installed dependency, returns `None` if it is missing
+    async fn read_metafile(&self, package_dir: &Path, name: &str) -> Result<Option<String>> {
+        match tokio::fs::read_to_string(resolve_in_root(
+            self.root.as_path(),
+            package_dir.join(name),
+        ))
+        .await
+        {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| {
+                format!(
+                    "Failed to read {} metafile of {}",
+                    name,
+                    package_dir.display()
+                )
+            }),
+        }
+    }
+
+    /// Parses the package identifiers in a dependency metafile, reporting malformed and duplicate entries
+    fn parse_deps(name: &str, data: &str, report: &mut PackageReport) -> BTreeSet<String> {
+        let mut deps = BTreeSet::new();
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match parse_fully_qualified_ident(line) {
+                Some(dep) => {
+                    if !deps.insert(dep.to_string()) {
+                        report.warnings.push(Finding::new(
+                            "DUPLICATE_DEP",
+                            format!("{} line {} lists {} more than once", name, index + 1, dep),
+                        ));
+                    }
+                }
+                None => report.errors.push(Finding::new(
+                    "INVALID_DEP",
+                    format!(
+                        "{} line {} is not a fully qualified package identifier: '{}'",
+                        name,
+                        index + 1,
+                        line
+                    ),
+                )),
+            }
+        }
+        deps
+    }
+
+    /// Checks that each `name=value` line of a metafile has a name and a value
+    fn check_assignments(name: &str, data: &str, report: &mut PackageReport) {
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {}
+                _ => report.errors.push(Finding::new(
+                    "ASSIGNMENT",
+                    format!(
+                        "{} line {} is not of the form 'name=value': '{}'",
+                        name,
+                        index + 1,
+                        line
+                    ),
+                )),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ArtifactCheck for MetafileCheck {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &MetafileCheck::METADATA
+    }
+    async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        let mut report = PackageReport::default();
+        let install_dir = self.install_dir.clone();
+        let ident = format!(
+            "{}/{}/{}/{}",
+            self.ident.origin, self.ident.name, self.ident.version, self.ident.release
+        );
+
+        match self.read_metafile(&install_dir, "IDENT").await? {
+            Some(data) => match parse_fully_qualified_ident(data.trim()) {
+                Some(package_ident) if package_ident.to_string() == ident => {}
+                Some(package_ident) => report.errors.push(Finding::new(
+                    "IDENT",
+                    format!(
+                        "IDENT metafile is {} but the artifact contains {}",
+                        package_ident, ident
+                    ),
+                )),
+                None => report.errors.push(Finding::new(
+                    "IDENT",
+                    format!(
+                        "IDENT metafile is not a fully qualified package identifier: '{}'",
+                        data.trim()
+                    ),
+                )),
+            },
+            None => report
+                .errors
+                .push(Finding::new("IDENT", "IDENT metafile is missing")),
+        }
+
+        match self.read_metafile(&install_dir, "TARGET").await? {
+            Some(data) => match PackageTarget::try_from(data.trim()) {
+                Ok(target) if target == self.ident.target => {}
+                Ok(target) => report.errors.push(Finding::new(
+                    "TARGET",
+                    format!(
+                        "TARGET metafile is {} but the artifact file name has target {}",
+                        target, self.ident.target
+                    ),
+                )),
+                Err(_) => report.errors.push(Finding::new(
+                    "TARGET",
+                    format!(
+                        "TARGET metafile is not a known package target: '{}'",
+                        data.trim()
+                    ),
+                )),
+            },
+            None => report
+                .errors
+                .push(Finding::new("TARGET", "TARGET metafile is missing")),
+        }
+
+        let mut dep_metafiles = HashMap::new();
+        for name in DEPENDENCY_METAFILES {
+            let deps = match self.read_metafile(&install_dir, name).await? {
+                Some(data) => MetafileCheck::parse_deps(name, &data, &mut report),
+                None => BTreeSet::new(),
+            };
+            if deps.contains(&ident) {
+                report.errors.push(Finding::new(
+                    "SELF_DEP",
+                    format!("{} metafile lists the package itself", name),
+                ));
+            }
+            dep_metafiles.insert(*name, deps);
+        }
+        for (deps_name, tdeps_name) in [("DEPS", "TDEPS"), ("BUILD_DEPS", "BUILD_TDEPS")] {
+            for dep in dep_metafiles[deps_name].difference(&dep_metafiles[tdeps_name]) {
+                report.errors.push(Finding::new(
+                    "TDEPS",
+                    format!(
+                        "{} is listed in {} but not in {}",
+                        dep, deps_name, tdeps_name
+                    ),
+                ));
+            }
+        }
+
+        // TDEPS must be the closure of DEPS, this can only be verified completely
+        // when the TDEPS of all the direct dependencies are available
+        let mut closure = dep_metafiles["DEPS"].clone();
+        let mut closure_complete = true;
+        for dep in dep_metafiles["DEPS"].iter() {
+            let dep_dir = HAB_PKGS_PATH.join(dep);
+            if self.read_metafile(&dep_dir, "IDENT").await?.is_none() {
+                debug!(
+                    "Dependency {} is not available, the TDEPS closure is not fully verified",
+                    dep
+                );
+                closure_complete = false;
+                continue;
+            }
+            if let Some(data) = self.read_metafile(&dep_dir, "TDEPS").await? {
+                closure.extend(
+                    data.lines()
+                        .filter_map(|line| parse_fully_qualified_ident(line.trim()))
+                        .map(|dep| dep.to_string()),
+                );
+            }
+        }
+        for dep in closure.difference(&dep_metafiles["TDEPS"]) {
+            if !dep_metafiles["DEPS"].contains(dep) {
+                report.errors.push(Finding::new(
+                    "TDEPS",
+                    format!(
+                        "{} is a transitive runtime dependency but is not listed in TDEPS",
+                        dep
+                    ),
+                ));
+            }
+        }
+        if closure_complete {
+            for dep in dep_metafiles["TDEPS"].difference(&closure) {
+                report.errors.push(Finding::new(
+                    "TDEPS",
+                    format!(
+                        "{} is listed in TDEPS but is not a transitive runtime dependency",
+                        dep
+                    ),
+                ));
+            }
+        }
+
+        // Search paths may only refer to the package and its runtime closure
+        let runtime_dirs = std::iter::once(install_dir.clone())
+            .chain(
+                dep_metafiles["TDEPS"]
+                    .iter()
+                    .map(|dep| HAB_PKGS_PATH.join(dep)),
+            )
+            .collect::<Vec<_>>();
+        for name in PATH_METAFILES {
+            let data = match self.read_metafile(&install_dir, name).await? {
+                Some(data) => data,
+                None => continue,
+            };
+            for entry in data.trim().split(':') {
+                let path = PathBuf::from(entry);
+                if entry.is_empty() || path.is_relative() {
+                    report.errors.push(Finding::new(
+                        "SEARCH_PATH",
+                        format!(
+                            "{} metafile has an entry that is not an absolute path: '{}'",
+                            name, entry
+                        ),
+                    ));
+                } else if !runtime_dirs.iter().any(|dir| path.starts_with(dir)) {
+                    report.errors.push(Finding::new(
+                        "SEARCH_PATH",
+                        format!(
+                        "{} metafile has an entry outside of the package and its runtime deps: {}",
+                        name, entry
+                    ),
+                    ));
+                } else if path.starts_with(&install_dir)
+                    && !resolve_in_root(self.root.as_path(), &path).is_dir()
+                {
+                    report.errors.push(Finding::new(
+                        "SEARCH_PATH",
+                        format!(
+                            "{} metafile has an entry that does not exist in the package: {}",
+                            name, entry
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(data) = self.read_metafile(&install_dir, "BINDS").await? {
+            MetafileCheck::check_assignments("BINDS", &data, &mut report);
+        }
+        if let Some(data) = self.read_metafile(&install_dir, "EXPORTS").await? {
+            MetafileCheck::check_assignments("EXPORTS", &data, &mut report);
+        }
+        if let Some(data) = self.read_metafile(&install_dir, "SVC_USER").await? {
+            let user = data.trim();
+            if user.is_empty() || user.contains(char::is_whitespace) {
+                report.errors.push(Finding::new(
+                    "SVC_USER",
+                    format!("SVC_USER metafile is not a user name: '{}'", user),
+                ));
+            }
+        }
+        match self.read_metafile(&install_dir, "MANIFEST").await? {
+            Some(data) if !data.trim().is_empty() => {}
+            Some(_) => report
+                .errors
+                .push(Finding::new("MANIFEST", "MANIFEST metafile is empty")),
+            None => report
+                .warnings
+                .push(Finding::new("MANIFEST", "MANIFEST metafile is missing")),
+        }
+        Ok(report)
+    }
+}
+
+/// Parses a package identifier that has all four of its parts
+fn parse_fully_qualified_ident(value: &str) -> Option<PackageIdent> {
+    PackageIdent::try_from(value).ok().filter(|ident| {
+        !ident.origin.is_empty()
+            && !ident.name.is_empty()
+            && !ident.version.is_empty()
+            && !ident.release.is_empty()
+    })
+}
+
+pub struct FileConflictCheck<'a> {
+    package_metadata: &'a PackageMetadata,
+    root: PathBuf,
+}
+
+impl<'a> FileConflictCheck<'a> {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "FILE_CONFLICTS",
+        description:
+            "Checks for files in bin, lib and include shipped by more than one package of the runtime closure",
+        default_severity: CheckSeverity::Off,
+        rules: &["CONFLICT"],
+    };
+
+    pub fn new(
+        package_metadata: &'a PackageMetadata,
+        root: impl AsRef<Path>,
+    ) -> FileConflictCheck<'a> {
+        FileConflictCheck {
+            package_metadata,
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> ArtifactCheck for FileConflictCheck<'a> {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &FileConflictCheck::METADATA
+    }
+    async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        let mut report = PackageReport::default();
+        for conflict in find_file_conflicts(self.root.as_path(), self.package_metadata).await? {
+            let issue = Finding::new(
+                "CONFLICT",
+                format!(
+                    "{} is shipped by multiple packages of the runtime closure: {}",
+                    conflict.path.display(),
+                    conflict
+                        .packages
+                        .iter()
+                        .map(|package| package.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+            // Conflicts between dependencies are not caused by the package itself
+            if conflict.packages.first() == self.package_metadata.ident.as_ref() {
+                report.errors.push(issue);
+            } else {
+                report.warnings.push(issue);
+            }
+        }
+        Ok(report)
+    }
+}
+
+pub struct RegressionCheck {
+    artifact: PackageArtifact,
+    thresholds: RegressionThresholds,
+}
+
+impl RegressionCheck {
+    pub const METADATA: CheckMetadata = CheckMetadata {
+        id: "REGRESSION",
+        description:
+            "Compares the artifact with the previous release of the package in the artifact cache",
+        default_severity: CheckSeverity::Warning,
+        rules: &[
+            "SIZE",
+            "FILE_COUNT",
+            "REMOVED_OBJECT",
+            "REMOVED_FILES",
+            "ADDED_FILES",
+            "NEEDED",
+            "EXPORTS",
+        ],
+    };
+
+    pub fn new(artifact: &PackageArtifact, thresholds: RegressionThresholds) -> RegressionCheck {
+        RegressionCheck {
+            artifact: PackageArtifact {
+                ident: artifact.ident.clone(),
+                path: artifact.path.clone(),
+            },
+            thresholds,
+        }
+    }
+
+    async fn compare_with_previous_release(&self) -> Result<Vec<Finding>> {
+        let previous_artifact = match regression::previous_release(&self.artifact.ident).await? {
+            Some(previous_artifact) => previous_artifact,
+            None => {
+                debug!(
+                    "No previous release of {} to compare with",
+                    self.artifact.path
+                );
+                return Ok(Vec::new());
+            }
+        };
+        info!(
+            "Comparing package artifact {} with {}",
+            self.artifact.path, previous_artifact.path
+        );
+        let summary = ArtifactSummary::new(&self.artifact).await?;
+        let previous_summary = ArtifactSummary::new(&previous_artifact).await?;
+        Ok(summary.compare(&previous_summary, &self.thresholds))
+    }
+}
+
+#[async_trait]
+impl ArtifactCheck for RegressionCheck {
+    fn metadata(&self) -> &'static CheckMetadata {
+        &RegressionCheck::METADATA
+    }
+    async fn visit_package_end(&mut self) -> Result<PackageReport> {
+        // A previous release that can no longer be read should not fail the check
+        let warnings = match self.compare_with_previous_release().await {
+            Ok(warnings) => warnings,
+            Err(err) => {
+                warn!(
+                    "Failed to compare {} with its previous release: {:#}",
+                    self.artifact.path, err
+                );
+                Vec::new()
+            }
+        };
+        Ok(PackageReport {
+            warnings,
+            ..Default::default()
+        })
+    }
+}
+
+/// The variables and fields of a pkg-config file, with variable references expanded
+#[derive(Debug, Default)]
+struct PkgConfigFile {
+    variables: HashMap<String, String>,
+    fields: HashMap<String, String>,
+}
+
+impl PkgConfigFile {
+    fn parse(data: &str) -> PkgConfigFile {
+        let mut pkg_config = PkgConfigFile::default();
+        for line in data.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let separator = line.find(['=', ':']);
+            let Some(separator) = separator else {
+                continue;
+            };
+            let (name, value) = (line[..separator].trim(), line[separator + 1..].trim());
+            let value = pkg_config.expand(value);
+            if line[separator..].starts_with('=') {
+                pkg_config.variables.insert(name.to_string(), value);
+            } else {
+                pkg_config.fields.insert(name.to_string(), value);
+            }
+        }
+        pkg_config
+    }
+
+    fn expand(&self, value: &str) -> String {
+        let mut expanded = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("${") {
+            expanded.push_str(&rest[..start]);
+            match rest[start..].find('}') {
+                Some(end) => {
+                    let name = &rest[start + 2..start + end];
+                    expanded.push_str(self.variables.get(name).map_or("", String::as_str));
+                    rest = &rest[start + end + 1..];
+                }
+                None => {
+                    rest = &rest[start..];
+                    break;
+                }
+            }
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+}
+
+/// Module names in a pkg-config `Requires` field, without their version constraints.
+/// Like pkg-config, comparison operators also separate words so `glib-2.0>=2.50` is
+/// read as the module `glib-2.0` followed by its version constraint.
+fn pkg_config_modules(requires: &str) -> Vec<String> {
+    let is_operator = |c: char| matches!(c, '<' | '>' | '=' | '!');
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in requires.chars() {
+        let is_separator = c == ',' || c.is_whitespace();
+        let starts_word = word
+            .chars()
+            .last()
+            .is_some_and(|last| is_operator(last) != is_operator(c));
+        if (is_separator || starts_word) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if !is_separator {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    let mut modules = Vec::new();
+    let mut skip_version = false;
+    for word in words {
+        if skip_version {
+            skip_version = false;
+        } else if word.starts_with(is_operator) {
+            skip_version = true;
         } else {
-            Ok(PackageReport::default())
+            modules.push(word);
+        }
+    }
+    modules
+}
+
+/// Checks if a path under `/hab/pkgs` belongs to the package or one of its runtime deps
+fn is_runtime_path(package_metadata: &PackageMetadata, path: &str) -> bool {
+    let parts = path
+        .trim_start_matches(HAB_PKGS_PATH.to_str().unwrap())
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .take(4)
+        .collect::<Vec<_>>();
+    if parts.len() < 2 {
+        return true;
+    }
+    package_metadata.all_runtime_deps().any(|dep| {
+        parts[0] == dep.origin
+            && parts[1] == dep.name
+            && parts.get(2).is_none_or(|version| *version == dep.version)
+            && parts.get(3).is_none_or(|release| *release == dep.release)
+    })
+}
+
+/// The parts of an ELF object where paths are stored, its dynamic string table and read-only data
+fn elf_strings<'a>(elf: &goblin::elf::Elf, data: &'a [u8]) -> Vec<&'a [u8]> {
+    elf.section_headers
+        .iter()
+        .filter(|header| {
+            matches!(
+                elf.shdr_strtab.get_at(header.sh_name),
+                Some(".dynstr" | ".rodata")
+            )
+        })
+        .filter_map(|header| header.file_range())
+        .filter_map(|range| data.get(range))
+        .collect()
+}
+
+/// Finds all absolute paths in the data, a path must not be preceded by a character that
+/// could be part of a path so that relative paths and urls are not matched. Paths directly
+/// following compiler flags such as `-L` or `-I` are matched.
+fn absolute_paths(data: &[u8]) -> impl Iterator<Item = String> + '_ {
+    let is_path_char =
+        |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'/' | b'.' | b'_' | b'-' | b'+' | b'@');
+    let mut index = 0;
+    std::iter::from_fn(move || {
+        while index < data.len() {
+            let start = index;
+            index += 1;
+            let is_flag = start >= 2
+                && data[start - 2] == b'-'
+                && data[start - 1].is_ascii_alphabetic()
+                && (start == 2 || !is_path_char(data[start - 3]));
+            if data[start] != b'/' || (start > 0 && is_path_char(data[start - 1]) && !is_flag) {
+                continue;
+            }
+            while index < data.len() && is_path_char(data[index]) {
+                index += 1;
+            }
+            let path = String::from_utf8_lossy(&data[start..index])
+                .trim_end_matches('.')
+                .to_string();
+            // A single component such as '/usr' is rarely a real reference
+            if path.len() > 1 && path[1..].contains('/') {
+                return Some(path);
+            }
         }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(licenses: &[&str]) -> LicenseScan {
+        LicenseScan {
+            evidence: licenses
+                .iter()
+                .map(|license| LicenseEvidence {
+                    license: license.to_string(),
+                    path: PathBuf::from("LICENSE"),
+                    score: 1.0,
+                    strategy: LicenseScanStrategy::Normal,
+                    location: LicenseLocation::Source,
+                })
+                .collect(),
+        }
+    }
+
+    fn messages(findings: &[Finding]) -> Vec<&str> {
+        findings
+            .iter()
+            .map(|finding| finding.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn additional_source_licenses_are_reported_despite_deprecated_ids() {
+        let pkg_licenses = LicenseExpression::parse("GPL-2.0").unwrap();
+        let mut report = FileReport::default();
+        report.warnings.push(Finding::new(
+            "DEPRECATED_LICENSE",
+            "Package license 'GPL-2.0' is a deprecated SPDX license identifier",
+        ));
+        compare_source_licenses(
+            Some(&pkg_licenses),
+            &detected(&["GPL-2.0-only", "MIT"]),
+            &mut report,
+        );
+        let warnings = messages(&report.warnings);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[1].contains("additional licenses were detected in source"));
+    }
+
+    #[test]
+    fn additional_source_licenses_are_not_reported_on_mismatch() {
+        let pkg_licenses = LicenseExpression::parse("Apache-2.0").unwrap();
+        let mut report = FileReport::default();
+        compare_source_licenses(Some(&pkg_licenses), &detected(&["MIT"]), &mut report);
+        let warnings = messages(&report.warnings);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains("which was not found in the source"));
+    }
+
+    #[test]
+    fn shared_library_names() {
+        assert!(is_shared_library_name("libfoo.so"));
+        assert!(is_shared_library_name("libfoo.so.1"));
+        assert!(is_shared_library_name("libfoo.so.1.2.3"));
+        assert!(is_shared_library_name("ld-linux-x86-64.so.2"));
+    }
+
+    #[test]
+    fn data_files_named_after_libraries_are_not_shared_libraries() {
+        assert!(!is_shared_library_name("libfoo.so.1.2.3-gdb.py"));
+        assert!(!is_shared_library_name("libfoo.so.1."));
+        assert!(!is_shared_library_name("foo.so"));
+        assert!(!is_shared_library_name(".so.1"));
+        assert!(!is_shared_library_name("libfoo.a"));
+    }
+
+    #[test]
+    fn absolute_paths_are_found_in_binary_data() {
+        let data = b"\0/hab/pkgs/core/glibc/2.36/20230101000000/lib\0see /usr/share/doc.\0";
+        assert_eq!(
+            absolute_paths(data).collect::<Vec<_>>(),
+            vec![
+                "/hab/pkgs/core/glibc/2.36/20230101000000/lib",
+                "/usr/share/doc"
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_paths_follow_compiler_flags() {
+        let data = b"-I/hab/cache/src/foo/include -L/usr/lib64";
+        assert_eq!(
+            absolute_paths(data).collect::<Vec<_>>(),
+            vec!["/hab/cache/src/foo/include", "/usr/lib64"]
+        );
+    }
+
+    #[test]
+    fn relative_paths_and_single_components_are_skipped() {
+        let data = b"share/man/man1 /usr x-/tmp/foo";
+        assert!(absolute_paths(data).next().is_none());
+    }
+
+    #[test]
+    fn pkg_config_modules_skip_version_constraints() {
+        assert_eq!(
+            pkg_config_modules("glib-2.0 >= 2.50, gobject-2.0 zlib"),
+            vec!["glib-2.0", "gobject-2.0", "zlib"]
+        );
+    }
+
+    #[test]
+    fn pkg_config_modules_split_on_operators() {
+        assert_eq!(
+            pkg_config_modules("glib-2.0>=2.50 libffi!=3.0,zlib= 1.2 openssl <3"),
+            vec!["glib-2.0", "libffi", "zlib", "openssl"]
+        );
+    }
+
+    #[test]
+    fn env_program_skips_options_and_assignments() {
+        assert_eq!(env_program(&["python3"]), (Some("python3"), false));
+        assert_eq!(
+            env_program(&["-i", "-u", "PATH", "LC_ALL=C", "perl", "-w"]),
+            (Some("perl"), false)
+        );
+        assert_eq!(env_program(&["-C", "/tmp", "sh"]), (Some("sh"), false));
+        assert_eq!(env_program(&["-i"]), (None, false));
+    }
+
+    #[test]
+    fn env_program_detects_split_strings() {
+        assert_eq!(
+            env_program(&["-S", "python3", "-u"]),
+            (Some("python3"), true)
+        );
+        assert_eq!(env_program(&["-Spython3"]), (Some("python3"), true));
+        assert_eq!(
+            env_program(&["--split-string=node", "--harmony"]),
+            (Some("node"), true)
+        );
     }
 }
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+use crate::{resolve_in_root, PackageIdent, PackageMetadata, HAB_PKGS_PATH};
+
+/// Directories whose files are found through search paths such as `PATH` or the library
+/// search path, when more than one package provides a file the search order decides which
+/// one is used.
+const CONFLICT_DIRS: &[&str] = &["bin", "lib", "include"];
+
+/// A file that is shipped by more than one package of a runtime closure
+#[derive(Debug, Clone, Serialize)]
+pub struct FileConflict {
+    /// Path of the file relative to the package installation directories
+    pub path: PathBuf,
+    /// Packages providing the file, the package itself comes first followed
+    /// by its dependencies in breadth first order
+    pub packages: Vec<PackageIdent>,
+}
+
+/// Finds the files in `bin`, `lib` and `include` that are shipped by more than one package
+/// in the runtime closure of the package. The closure is found by following the `DEPS`
+/// metafiles of the packages available in `root`, falling back to the installed packages.
+pub async fn find_file_conflicts(
+    root: impl AsRef<Path>,
+    metadata: &PackageMetadata,
+) -> Result<Vec<FileConflict>> {
+    let root = root.as_ref();
+    let mut files: BTreeMap<PathBuf, Vec<PackageIdent>> = BTreeMap::new();
+    for package in runtime_closure(root, metadata).await {
+        let package_dir = HAB_PKGS_PATH.join(PathBuf::from(&package));
+        for dir in CONFLICT_DIRS {
+            for path in package_files(resolve_in_root(root, package_dir.join(dir))).await? {
+                files
+                    .entry(PathBuf::from(dir).join(path))
+                    .or_default()
+                    .push(package.clone());
+            }
+        }
+    }
+    Ok(files
+        .into_iter()
+        .filter(|(_, packages)| packages.len() > 1)
+        .map(|(path, packages)| FileConflict { path, packages })
+        .collect())
+}
+
+/// The package followed by its transitive runtime dependencies in breadth first order,
+/// dependencies that are not available are skipped.
+async fn runtime_closure(root: &Path, metadata: &PackageMetadata) -> Vec<PackageIdent> {
+    let mut closure = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    let mut deps = metadata.deps.iter().cloned().collect::<Vec<_>>();
+    deps.sort_by_key(|dep| dep.to_string());
+    if let Some(ident) = metadata.ident.as_ref() {
+        visited.insert(ident.clone());
+        closure.push(ident.clone());
+    }
+    for dep in deps {
+        if visited.insert(dep.clone()) {
+            queue.push_back(dep);
+        }
+    }
+    while let Some(package) = queue.pop_front() {
+        let package_dir = resolve_in_root(root, HAB_PKGS_PATH.join(PathBuf::from(&package)));
+        let dep_metadata = match PackageMetadata::new(package_dir.as_path()).await {
+            Ok(dep_metadata) => dep_metadata,
+            Err(err) => {
+                debug!(
+                    "Dependency {} is not available, its files are not checked for conflicts: {:#}",
+                    package, err
+                );
+                continue;
+            }
+        };
+        let mut deps = dep_metadata.deps.into_iter().collect::<Vec<_>>();
+        deps.sort_by_key(|dep| dep.to_string());
+        for dep in deps {
+            if visited.insert(dep.clone()) {
+                queue.push_back(dep);
+            }
+        }
+        closure.push(package);
+    }
+    closure
+}
+
+/// Lists the files and symlinks below a directory relative to it, symlinks to
+/// directories are not followed.
+async fn package_files(dir: PathBuf) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(rel_dir) = dirs.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(dir.join(rel_dir.as_path())).await else {
+            continue;
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let rel_path = rel_dir.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                dirs.push(rel_path);
+            } else {
+                files.push(rel_path);
+            }
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,115 @@
+use petgraph::{
+    algo::{greedy_feedback_arc_set, tarjan_scc},
+    stable_graph::NodeIndex,
+    visit::EdgeRef,
+};
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{DependencyType, PackageDependencyGraph};
+
+/// A strongly connected component of the dependency graph
+pub struct DependencyCycle {
+    pub packages: Vec<NodeIndex>,
+    pub edges: Vec<(NodeIndex, NodeIndex, DependencyType)>,
+    pub suggested_cuts: Vec<(NodeIndex, NodeIndex, DependencyType)>,
+}
+
+impl DependencyCycle {
+    /// Finds all cycles in the dependency graph along with a small set of
+    /// edges that must be removed to break each of them.
+    pub fn detect(dep_graph: &PackageDependencyGraph) -> Vec<DependencyCycle> {
+        let mut cycles = Vec::new();
+        for component in tarjan_scc(&**dep_graph) {
+            if component.len() == 1 && !dep_graph.contains_edge(component[0], component[0]) {
+                continue;
+            }
+            let members = component.iter().copied().collect::<HashSet<_>>();
+            let cycle_graph = dep_graph.filter_map(
+                |node_index, _| {
+                    if members.contains(&node_index) {
+                        Some(node_index)
+                    } else {
+                        None
+                    }
+                },
+                |_, edge| Some(*edge),
+            );
+            let edges = cycle_graph
+                .edge_references()
+                .map(|edge| {
+                    (
+                        cycle_graph[edge.source()],
+                        cycle_graph[edge.target()],
+                        *edge.weight(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let suggested_cuts = greedy_feedback_arc_set(&cycle_graph)
+                .map(|edge| {
+                    (
+                        cycle_graph[edge.source()],
+                        cycle_graph[edge.target()],
+                        *edge.weight(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let mut packages = component;
+            packages.sort_by_key(|node| dep_graph[*node].plan.ident.to_string());
+            cycles.push(DependencyCycle {
+                packages,
+                edges,
+                suggested_cuts,
+            });
+        }
+        cycles.sort_by_key(|cycle| std::cmp::Reverse(cycle.packages.len()));
+        cycles
+    }
+
+    pub fn report(&self, dep_graph: &PackageDependencyGraph, index: usize) -> String {
+        let mut output = String::new();
+        writeln!(
+            output,
+            "Cycle {} with {} packages:",
+            index,
+            self.packages.len()
+        )
+        .unwrap();
+        for package in self.packages.iter() {
+            writeln!(output, "  {}", dep_graph[*package].plan.ident).unwrap();
+        }
+        writeln!(output, "  Dependencies:").unwrap();
+        for (source, target, dependency_type) in self.edges.iter() {
+            writeln!(
+                output,
+                "    {} -> {} [{:?}]",
+                dep_graph[*source].plan.ident, dep_graph[*target].plan.ident, dependency_type
+            )
+            .unwrap();
+        }
+        writeln!(output, "  Suggested dependencies to break:").unwrap();
+        for (source, target, dependency_type) in self.suggested_cuts.iter() {
+            let source = &dep_graph[*source].plan.ident;
+            let target = &dep_graph[*target].plan.ident;
+            let suggestion = match dependency_type {
+                DependencyType::Runtime => format!(
+                    "runtime dependency, consider moving {}/{} from pkg_deps to pkg_build_deps",
+                    target.origin, target.name
+                ),
+                DependencyType::Build => format!(
+                    "build dependency, consider removing {}/{} from pkg_build_deps",
+                    target.origin, target.name
+                ),
+                DependencyType::Studio => {
+                    String::from("studio dependency, consider making the package a native package")
+                }
+            };
+            writeln!(
+                output,
+                "    {} -> {} [{:?}]: {}",
+                source, target, dependency_type, suggestion
+            )
+            .unwrap();
+        }
+        output
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use petgraph::{algo::tarjan_scc, stable_graph::NodeIndex, visit::EdgeRef};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+    path::PathBuf,
+    sync::Arc,
+};
+use tempdir::TempDir;
+use tracing::{debug, info, warn};
+
+use crate::{
+    dep_graph_build,
+    git::{git, git_toplevel},
+    HabitatAutoBuildConfiguration, PackageDependencyGraph, PackageNode, Scripts,
+};
+
+/// A set of git worktrees checked out at a given revision, one for each git
+/// repository containing a configured plan repo.
+pub struct RevisionWorktree {
+    pub revision: String,
+    pub config: HabitatAutoBuildConfiguration,
+    worktrees: Vec<(PathBuf, PathBuf)>,
+    tmp_dir: TempDir,
+}
+
+impl RevisionWorktree {
+    pub async fn new(
+        revision: &str,
+        config: &HabitatAutoBuildConfiguration,
+    ) -> Result<RevisionWorktree> {
+        let mut revision_worktree = RevisionWorktree {
+            revision: revision.to_owned(),
+            config: config.clone(),
+            worktrees: Vec::new(),
+            tmp_dir: TempDir::new("hab-auto-build-diff")?,
+        };
+        // Worktrees created before a failure would otherwise be left registered in the repos
+        if let Err(err) = revision_worktree.add_worktrees(config).await {
+            if let Err(remove_err) = revision_worktree.remove().await {
+                warn!("{:#}", remove_err);
+            }
+            return Err(err);
+        }
+        Ok(revision_worktree)
+    }
+
+    /// Checks out a worktree for each git repository of the configured plan repos
+    /// and points the repos of the worktree configuration into them.
+    async fn add_worktrees(&mut self, config: &HabitatAutoBuildConfiguration) -> Result<()> {
+        let revision = self.revision.as_str();
+        let mut git_roots: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for repo_config in self.config.repos.iter_mut() {
+            let repo_path = if repo_config.source.is_absolute() {
+                repo_config.source.canonicalize()?
+            } else {
+                config
+                    .config_path
+                    .parent()
+                    .unwrap()
+                    .join(repo_config.source.as_path())
+                    .canonicalize()?
+            };
+            let git_root = git_toplevel(repo_path.as_path()).await?;
+            let worktree_path = match git_roots.get(&git_root) {
+                Some(worktree_path) => worktree_path.clone(),
+                None => {
+                    let worktree_path = self
+                        .tmp_dir
+                        .path()
+                        .join(format!("worktree-{}", git_roots.len()));
+                    debug!(
+                        "Creating worktree for {} at revision {} in {}",
+                        git_root.display(),
+                        revision,
+                        worktree_path.display()
+                    );
+                    git(
+                        git_root.as_path(),
+                        &[
+                            "worktree",
+                            "add",
+                            "--detach",
+                            worktree_path.to_str().unwrap(),
+                            revision,
+                        ],
+                    )
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to create worktree for '{}' at revision '{}'",
+                            git_root.display(),
+                            revision
+                        )
+                    })?;
+                    git_roots.insert(git_root.clone(), worktree_path.clone());
+                    self.worktrees
+                        .push((git_root.clone(), worktree_path.clone()));
+                    worktree_path
+                }
+            };
+            repo_config.source = worktree_path.join(repo_path.strip_prefix(git_root.as_path())?);
+        }
+        Ok(())
+    }
+
+    pub async fn remove(self) -> Result<()> {
+        for (git_root, worktree_path) in self.worktrees.iter() {
+            git(
+                git_root.as_path(),
+                &[
+                    "worktree",
+                    "remove",
+                    "--force",
+                    worktree_path.to_str().unwrap(),
+                ],
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to remove worktree '{}' for '{}'",
+                    worktree_path.display(),
+                    git_root.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    pub async fn summary(&self, scripts: Arc<Scripts>) -> Result<GraphSummary> {
+        info!("Building dependency graph at revision {}", self.revision);
+        let (dep_graph, _, _, _) =
+            dep_graph_build(vec![], &self.config, false, false, None, scripts).await?;
+        Ok(GraphSummary::new(&dep_graph))
+    }
+}
+
+/// Version independent description of a dependency graph that can be
+/// compared across revisions.
+pub struct GraphSummary {
+    versions: BTreeMap<String, String>,
+    edges: BTreeSet<(String, String, String)>,
+    cycles: BTreeSet<BTreeSet<String>>,
+    runtime_closures: BTreeMap<String, BTreeSet<String>>,
+    build_closures: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl GraphSummary {
+    pub fn new(dep_graph: &PackageDependencyGraph) -> GraphSummary {
+        let name = |node: NodeIndex| {
+            let ident = &dep_graph[node].plan.ident;
+            format!("{}/{}", ident.origin, ident.name)
+        };
+        let mut versions = BTreeMap::new();
+        let mut runtime_closures = BTreeMap::new();
+        let mut build_closures = BTreeMap::new();
+        for node in dep_graph.node_indices() {
+            versions.insert(name(node), dep_graph[node].plan.ident.to_string());
+            let package_node = PackageNode(node);
+            runtime_closures.insert(
+                name(node),
+                package_node
+                    .runtime_closure(dep_graph)
+                    .into_iter()
+                    .filter(|dep| *dep != node)
+                    .map(name)
+                    .collect(),
+            );
+            build_closures.insert(
+                name(node),
+                package_node
+                    .build_closure(dep_graph)
+                    .into_iter()
+                    .filter(|dep| *dep != node)
+                    .map(name)
+                    .collect(),
+            );
+        }
+        let edges = dep_graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    name(edge.source()),
+                    name(edge.target()),
+                    format!("{:?}", edge.weight()),
+                )
+            })
+            .collect();
+        let cycles = tarjan_scc(&**dep_graph)
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || dep_graph.contains_edge(component[0], component[0])
+            })
+            .map(|component| component.into_iter().map(name).collect())
+            .collect();
+        GraphSummary {
+            versions,
+            edges,
+            cycles,
+            runtime_closures,
+            build_closures,
+        }
+    }
+
+    /// Renders a report of the changes between this graph and a newer one
+    pub fn diff(&self, other: &GraphSummary, packages: &[String]) -> String {
+        let mut output = String::new();
+        let is_selected =
+            |package: &String| packages.is_empty() || packages.iter().any(|p| p == package);
+
+        writeln!(output, "Packages:").unwrap();
+        for (package, version) in other.versions.iter() {
+            match self.versions.get(package) {
+                Some(previous_version) if previous_version != version => {
+                    writeln!(output, "  ~ {} -> {}", previous_version, version).unwrap()
+                }
+                Some(_) => {}
+                None => writeln!(output, "  + {}", version).unwrap(),
+            }
+        }
+        for (package, version) in self.versions.iter() {
+            if !other.versions.contains_key(package) {
+                writeln!(output, "  - {}", version).unwrap();
+            }
+        }
+
+        writeln!(output, "Dependencies:").unwrap();
+        for (source, target, dependency_type) in other.edges.difference(&self.edges) {
+            writeln!(output, "  + {} -> {} [{}]", source, target, dependency_type).unwrap();
+        }
+        for (source, target, dependency_type) in self.edges.difference(&other.edges) {
+            writeln!(output, "  - {} -> {} [{}]", source, target, dependency_type).unwrap();
+        }
+
+        for (label, previous_closures, closures) in [
+            (
+                "Runtime closures",
+                &self.runtime_closures,
+                &other.runtime_closures,
+            ),
+            (
+                "Build closures",
+                &self.build_closures,
+                &other.build_closures,
+            ),
+        ] {
+            writeln!(output, "{}:", label).unwrap();
+            for (package, closure) in closures.iter().filter(|(p, _)| is_selected(p)) {
+                if let Some(previous_closure) = previous_closures.get(package) {
+                    if previous_closure == closure {
+                        continue;
+                    }
+                    writeln!(output, "  {}", package).unwrap();
+                    for dep in closure.difference(previous_closure) {
+                        writeln!(output, "    + {}", dep).unwrap();
+                    }
+                    for dep in previous_closure.difference(closure) {
+                        writeln!(output, "    - {}", dep).unwrap();
+                    }
+                }
+            }
+        }
+
+        let new_cycles = other.cycles.difference(&self.cycles).collect::<Vec<_>>();
+        if !new_cycles.is_empty() {
+            warn!("{} new dependency cycles introduced", new_cycles.len());
+        }
+        writeln!(output, "New cycles:").unwrap();
+        for cycle in new_cycles {
+            writeln!(
+                output,
+                "  {}",
+                cycle.iter().cloned().collect::<Vec<_>>().join(" <-> ")
+            )
+            .unwrap();
+        }
+        output
+    }
+}
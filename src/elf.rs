@@ -0,0 +1,306 @@
+use anyhow::Result;
+use goblin::{
+    elf::{header::EM_NONE, symver::VER_FLG_WEAK},
+    Object,
+};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::trace;
+
+use crate::{resolve_in_root, PackageIdent, HAB_PKGS_PATH};
+
+/// The parts of an ELF object's dynamic section that matter to the dynamic linker
+#[derive(Debug)]
+pub struct ElfObject {
+    /// Location of the object at runtime, used to expand `$ORIGIN`
+    pub path: PathBuf,
+    pub interpreter: Option<PathBuf>,
+    pub is_64: bool,
+    pub machine: u16,
+    pub needed: Vec<String>,
+    pub rpaths: Vec<PathBuf>,
+    pub runpaths: Vec<PathBuf>,
+    /// Non-weak symbol versions required from each needed library
+    pub version_needs: Vec<(String, String)>,
+    /// Symbol versions defined by the object
+    pub version_defs: HashSet<String>,
+}
+
+impl ElfObject {
+    /// Parses the object in `data`, returns `None` if the data is not an ELF object
+    pub fn parse(data: &[u8], path: impl AsRef<Path>) -> Result<Option<ElfObject>> {
+        let elf = match Object::parse(data)? {
+            Object::Elf(elf) => elf,
+            _ => return Ok(None),
+        };
+        let path = path.as_ref().to_path_buf();
+        let origin = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let search_paths = |entries: &[&str]| {
+            entries
+                .iter()
+                .flat_map(|v| v.split(':'))
+                .filter(|v| !v.is_empty())
+                .map(|v| expand_origin(v, origin.as_path()))
+                .collect::<Vec<_>>()
+        };
+        let mut version_needs = Vec::new();
+        if let Some(verneed) = elf.verneed.as_ref() {
+            for need_file in verneed.iter() {
+                let Some(library) = elf.dynstrtab.get_at(need_file.vn_file) else {
+                    continue;
+                };
+                for need_version in need_file.iter() {
+                    if need_version.vna_flags & VER_FLG_WEAK != 0 {
+                        continue;
+                    }
+                    if let Some(version) = elf.dynstrtab.get_at(need_version.vna_name) {
+                        version_needs.push((library.to_string(), version.to_string()));
+                    }
+                }
+            }
+        }
+        let mut version_defs = HashSet::new();
+        if let Some(verdef) = elf.verdef.as_ref() {
+            for def in verdef.iter() {
+                // The first auxiliary entry names the version, the others are its parents
+                if let Some(name) = def
+                    .iter()
+                    .next()
+                    .and_then(|aux| elf.dynstrtab.get_at(aux.vda_name))
+                {
+                    version_defs.insert(name.to_string());
+                }
+            }
+        }
+        Ok(Some(ElfObject {
+            interpreter: elf.interpreter.map(PathBuf::from),
+            is_64: elf.is_64,
+            machine: elf.header.e_machine,
+            needed: elf.libraries.iter().map(|v| v.to_string()).collect(),
+            rpaths: search_paths(&elf.rpaths),
+            runpaths: search_paths(&elf.runpaths),
+            version_needs,
+            version_defs,
+            path,
+        }))
+    }
+
+    /// Checks if the dynamic linker would load `library` for this object
+    fn is_compatible(&self, library: &ElfObject) -> bool {
+        self.is_64 == library.is_64
+            && (self.machine == library.machine
+                || self.machine == EM_NONE
+                || library.machine == EM_NONE)
+    }
+}
+
+/// A shared library loaded for an ELF object
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedLibrary {
+    pub name: String,
+    pub path: PathBuf,
+    pub needed_by: PathBuf,
+    /// The hab package that contains the library
+    pub package: Option<PackageIdent>,
+}
+
+/// A shared library that could not be found in any search path
+#[derive(Debug, Clone)]
+pub struct MissingLibrary {
+    pub name: String,
+    pub needed_by: PathBuf,
+    pub search_paths: Vec<PathBuf>,
+}
+
+/// A symbol version that is not defined by the library that was loaded
+#[derive(Debug, Clone)]
+pub struct MissingVersion {
+    pub library: PathBuf,
+    pub version: String,
+    pub needed_by: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct LibraryResolution {
+    /// Libraries in the order they are loaded
+    pub libraries: Vec<ResolvedLibrary>,
+    pub missing_libraries: Vec<MissingLibrary>,
+    pub missing_versions: Vec<MissingVersion>,
+}
+
+/// Resolves shared libraries the way `ld.so` does, following the dependencies of
+/// every loaded library. Paths are resolved within `root` where the runtime
+/// dependencies of the package are unpacked.
+pub struct LibraryResolver {
+    root: PathBuf,
+    objects: HashMap<PathBuf, Option<Arc<ElfObject>>>,
+}
+
+impl LibraryResolver {
+    pub fn new(root: impl AsRef<Path>) -> LibraryResolver {
+        LibraryResolver {
+            root: root.as_ref().to_path_buf(),
+            objects: HashMap::new(),
+        }
+    }
+
+    /// Loads the ELF object at the given runtime path, objects are cached as
+    /// the same libraries are needed by most files in a package.
+    async fn load(&mut self, path: &Path) -> Option<Arc<ElfObject>> {
+        if let Some(object) = self.objects.get(path) {
+            return object.clone();
+        }
+        let resolved_path = resolve_in_root(self.root.as_path(), path);
+        let object = match tokio::fs::read(resolved_path.as_path()).await {
+            Ok(data) => match ElfObject::parse(&data, path) {
+                Ok(object) => object.map(Arc::new),
+                Err(err) => {
+                    trace!(
+                        "Failed to parse {} as ELF: {}",
+                        resolved_path.display(),
+                        err
+                    );
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        self.objects.insert(path.to_path_buf(), object.clone());
+        object
+    }
+
+    /// Resolves the libraries of the object, `fallback_paths` are searched last when the
+    /// object has no interpreter, as is the case for shared libraries.
+    pub async fn resolve(
+        &mut self,
+        object: Arc<ElfObject>,
+        fallback_paths: &[PathBuf],
+    ) -> LibraryResolution {
+        let mut resolution = LibraryResolution::default();
+        // The interpreter's library directory is the default search path of the dynamic linker
+        let default_paths = object
+            .interpreter
+            .as_ref()
+            .and_then(|interpreter| interpreter.parent())
+            .map(|path| vec![path.to_path_buf()])
+            .unwrap_or_else(|| fallback_paths.to_vec());
+        let mut loaded: HashMap<String, Arc<ElfObject>> = HashMap::new();
+        let mut missing = HashSet::new();
+        let mut processed = vec![];
+        let mut queue = VecDeque::new();
+        queue.push_back((object, Vec::<PathBuf>::new()));
+        while let Some((object, loader_rpaths)) = queue.pop_front() {
+            // DT_RPATH of the object and of the objects that loaded it are only used if
+            // the object has no DT_RUNPATH, in which case only the DT_RUNPATH is used.
+            let rpaths = if object.runpaths.is_empty() {
+                object
+                    .rpaths
+                    .iter()
+                    .chain(loader_rpaths.iter())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            } else {
+                loader_rpaths
+            };
+            let search_paths = if object.runpaths.is_empty() {
+                rpaths.iter().chain(default_paths.iter())
+            } else {
+                object.runpaths.iter().chain(default_paths.iter())
+            }
+            .cloned()
+            .collect::<Vec<_>>();
+            for name in object.needed.iter() {
+                if loaded.contains_key(name) || missing.contains(name) {
+                    continue;
+                }
+                let candidates = if name.contains('/') {
+                    vec![PathBuf::from(name)]
+                } else {
+                    search_paths.iter().map(|dir| dir.join(name)).collect()
+                };
+                let mut library = None;
+                for candidate in candidates {
+                    if let Some(candidate_object) = self.load(candidate.as_path()).await {
+                        if object.is_compatible(&candidate_object) {
+                            library = Some(candidate_object);
+                            break;
+                        }
+                        trace!(
+                            "Skipping incompatible library {} for {}",
+                            candidate.display(),
+                            object.path.display()
+                        );
+                    }
+                }
+                if let Some(library) = library {
+                    trace!(
+                        "For {} library {} found in {}",
+                        object.path.display(),
+                        name,
+                        library.path.display()
+                    );
+                    resolution.libraries.push(ResolvedLibrary {
+                        name: name.clone(),
+                        path: library.path.clone(),
+                        needed_by: object.path.clone(),
+                        package: package_ident(library.path.as_path()),
+                    });
+                    loaded.insert(name.clone(), library.clone());
+                    queue.push_back((library, rpaths.clone()));
+                } else {
+                    missing.insert(name.clone());
+                    resolution.missing_libraries.push(MissingLibrary {
+                        name: name.clone(),
+                        needed_by: object.path.clone(),
+                        search_paths: search_paths.clone(),
+                    });
+                }
+            }
+            processed.push(object);
+        }
+        for object in processed.iter() {
+            for (name, version) in object.version_needs.iter() {
+                if let Some(library) = loaded.get(name) {
+                    // Libraries without any version definitions accept all versions
+                    if !library.version_defs.is_empty() && !library.version_defs.contains(version) {
+                        resolution.missing_versions.push(MissingVersion {
+                            library: library.path.clone(),
+                            version: version.clone(),
+                            needed_by: object.path.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        resolution
+    }
+}
+
+/// Returns the package a file under the hab package directory belongs to
+pub fn package_ident(path: impl AsRef<Path>) -> Option<PackageIdent> {
+    let mut components = path
+        .as_ref()
+        .strip_prefix(HAB_PKGS_PATH.as_path())
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string());
+    Some(PackageIdent {
+        origin: components.next()?,
+        name: components.next()?,
+        version: components.next()?,
+        release: components.next()?,
+    })
+}
+
+fn expand_origin(entry: &str, origin: &Path) -> PathBuf {
+    let origin = origin.to_string_lossy();
+    PathBuf::from(
+        entry
+            .replace("${ORIGIN}", &origin)
+            .replace("$ORIGIN", &origin),
+    )
+}
@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Component, Path, PathBuf},
+};
+use tracing::{debug, trace};
+
+lazy_static! {
+    static ref SOURCE_COMMAND_PREFIXES: Vec<&'static str> = vec!["source ", ". "];
+    static ref PLAN_DIR_VARIABLES: Vec<&'static str> = vec![
+        "${PLAN_CONTEXT}",
+        "$PLAN_CONTEXT",
+        "$(dirname \"${BASH_SOURCE[0]}\")",
+        "$(dirname ${BASH_SOURCE[0]})",
+        "$(dirname \"$0\")",
+        "$(dirname $0)",
+    ];
+}
+
+pub async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("Failed to invoke git command")?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(anyhow!(
+            "git {} exited with {:?}: {}",
+            args.join(" "),
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+pub async fn git_toplevel(dir: &Path) -> Result<PathBuf> {
+    Ok(PathBuf::from(
+        git(dir, &["rev-parse", "--show-toplevel"])
+            .await
+            .with_context(|| format!("'{}' is not inside a git repository", dir.display()))?
+            .trim(),
+    )
+    .canonicalize()?)
+}
+
+/// Files that have changed between a git ref and the current working tree
+/// of one or more git repositories, including uncommitted and untracked files.
+#[derive(Debug, Default)]
+pub struct GitChanges {
+    git_ref: String,
+    checked_roots: HashMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl GitChanges {
+    pub fn new(git_ref: impl Into<String>) -> GitChanges {
+        GitChanges {
+            git_ref: git_ref.into(),
+            checked_roots: HashMap::new(),
+        }
+    }
+
+    /// Returns the absolute paths of all files changed in the git repository containing `dir`
+    pub async fn changed_files(&mut self, dir: &Path) -> Result<&BTreeSet<PathBuf>> {
+        let git_root = git_toplevel(dir).await?;
+        if !self.checked_roots.contains_key(&git_root) {
+            let mut changed_files = BTreeSet::new();
+            let diff = git(
+                git_root.as_path(),
+                &["diff", "--name-only", self.git_ref.as_str()],
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to determine changes in '{}' since '{}'",
+                    git_root.display(),
+                    self.git_ref
+                )
+            })?;
+            let untracked = git(
+                git_root.as_path(),
+                &["ls-files", "--others", "--exclude-standard"],
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to determine untracked files in '{}'",
+                    git_root.display()
+                )
+            })?;
+            for file in diff.lines().chain(untracked.lines()) {
+                if !file.is_empty() {
+                    changed_files.insert(git_root.join(file));
+                }
+            }
+            debug!(
+                "Found {} files changed in {} since {}",
+                changed_files.len(),
+                git_root.display(),
+                self.git_ref
+            );
+            self.checked_roots.insert(git_root.clone(), changed_files);
+        }
+        Ok(&self.checked_roots[&git_root])
+    }
+}
+
+/// Returns the files sourced by a plan file, the paths are resolved relative
+/// to the plan's directory where possible. Sourced paths that still contain
+/// unresolved shell variables are returned as is.
+pub async fn plan_sourced_files(plan_path: &Path) -> Result<Vec<PathBuf>> {
+    let plan_dir = plan_path.parent().unwrap();
+    let data = tokio::fs::read_to_string(plan_path)
+        .await
+        .with_context(|| format!("Failed to read plan file '{}'", plan_path.display()))?;
+    let mut sourced_files = Vec::new();
+    for line in data.lines() {
+        let line = line.trim();
+        for prefix in SOURCE_COMMAND_PREFIXES.iter() {
+            if let Some(sourced_file) = line.strip_prefix(prefix) {
+                let mut sourced_file = sourced_file
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_matches(&['"', '\''] as &[_])
+                    .to_string();
+                for variable in PLAN_DIR_VARIABLES.iter() {
+                    sourced_file =
+                        sourced_file.replace(variable, plan_dir.to_str().unwrap_or_default());
+                }
+                if sourced_file.is_empty() {
+                    continue;
+                }
+                let sourced_file = PathBuf::from(sourced_file);
+                let sourced_file = if sourced_file.is_relative() {
+                    normalize_path(plan_dir.join(sourced_file))
+                } else {
+                    normalize_path(sourced_file)
+                };
+                trace!(
+                    "Plan {} sources {}",
+                    plan_path.display(),
+                    sourced_file.display()
+                );
+                sourced_files.push(sourced_file);
+            }
+        }
+    }
+    Ok(sourced_files)
+}
+
+/// Checks if a path sourced by a plan refers to the given file
+pub fn sourced_file_matches(sourced_file: &Path, file: &Path) -> bool {
+    if sourced_file == file {
+        true
+    } else if sourced_file.to_string_lossy().contains('$') {
+        // We could not resolve all the variables in the path, so
+        // we fall back to comparing the file names
+        sourced_file.file_name().is_some() && sourced_file.file_name() == file.file_name()
+    } else {
+        false
+    }
+}
+
+fn normalize_path(path: PathBuf) -> PathBuf {
+    let mut normalized_path = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized_path.pop();
+            }
+            Component::CurDir => {}
+            component => normalized_path.push(component),
+        }
+    }
+    normalized_path
+}
@@ -1,23 +1,38 @@
 mod checker;
+mod conflicts;
+mod cycles;
+mod diff;
+mod elf;
+mod git;
+mod regression;
+mod sbom;
 mod server;
+mod spdx;
+mod visualize;
 
 use anyhow::{anyhow, Context, Result};
-use checker::{ArtifactChecker, LicenseCheck};
+use checker::{
+    ArtifactChecker, ArtifactReport, CheckConfiguration, CheckRegistry, CheckSummary, LicenseCheck,
+    ReportFormat, SourceConfiguration,
+};
 use chrono::{DateTime, Utc};
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use core::cmp::Ordering;
+use cycles::DependencyCycle;
 use dashmap::DashSet;
 use futures::{stream::FuturesUnordered, StreamExt};
+use git::{plan_sourced_files, sourced_file_matches, GitChanges};
 use inquire::{Confirm, MultiSelect};
 use names::{Generator, Name};
 use petgraph::{
     algo::{self, greedy_feedback_arc_set},
-    dot::{Config, Dot},
     stable_graph::{EdgeIndex, NodeIndex},
     visit::{EdgeRef, IntoNodeReferences, NodeFiltered},
     Direction, Graph,
 };
+use regression::RegressionThresholds;
 use reqwest::Url;
+use sbom::{Sbom, SbomFormat};
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Borrow,
@@ -25,9 +40,9 @@ use std::{
     env,
     ffi::OsString,
     fmt::{self, Display},
-    io::BufRead,
+    io::{BufRead, Read},
     ops::Deref,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     process::Stdio,
     str::FromStr,
     sync::Arc,
@@ -42,6 +57,7 @@ use tokio::{
     task::JoinHandle,
 };
 use tracing::{debug, error, info, trace, warn};
+use visualize::{GraphClustering, GraphExport, GraphFormat};
 use xz2::bufread::XzDecoder;
 
 lazy_static::lazy_static! {
@@ -89,7 +105,13 @@ lazy_static::lazy_static! {
     static ref STUDIO_INSTALLED: Arc<RwLock<bool>> = Arc::new(RwLock::new(false));
 }
 
-#[derive(Debug, Clone)]
+/// Whether a file at the top of a package install directory is a metafile such as
+/// IDENT or MANIFEST, which are all named in upper case.
+pub fn is_metafile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ValidFilePath(PathBuf);
 
 impl ValidFilePath {
@@ -127,36 +149,7 @@ impl PackageArtifact {
     pub async fn new(path: &ValidFilePath) -> Result<PackageArtifact> {
         let path = path.clone();
         tokio::task::spawn_blocking(move || {
-            let f = std::fs::File::open(path.as_ref())?;
-            let mut reader = std::io::BufReader::new(f);
-
-            // We skip the first 5 lines
-            let mut line = String::new();
-            let mut skip_lines = 5;
-            loop {
-                match reader.read_line(&mut line) {
-                    Ok(0) => {
-                        return Err(anyhow!("The file {} is not a valid .hart file", path));
-                    }
-                    Ok(_) => {
-                        skip_lines -= 1;
-                        if skip_lines == 0 {
-                            break;
-                        } else {
-                            continue;
-                        }
-                    }
-                    Err(err) => {
-                        return Err(anyhow!(
-                            "The file {} is not a valid .hart file: {:?}",
-                            path,
-                            err
-                        ));
-                    }
-                }
-            }
-            let decoder = XzDecoder::new(reader);
-            let mut tar = Archive::new(decoder);
+            let mut tar = PackageArtifact::open_archive(&path)?;
             let mut entries = tar.entries()?;
             let first_entry = entries
                 .next()
@@ -244,18 +237,258 @@ impl PackageArtifact {
         })
         .await?
     }
+    fn open_archive(
+        path: &ValidFilePath,
+    ) -> Result<Archive<XzDecoder<std::io::BufReader<std::fs::File>>>> {
+        let f = std::fs::File::open(path.as_ref())?;
+        let mut reader = std::io::BufReader::new(f);
+
+        // We skip the first 5 lines
+        let mut line = String::new();
+        let mut skip_lines = 5;
+        loop {
+            match reader.read_line(&mut line) {
+                Ok(0) => {
+                    return Err(anyhow!("The file {} is not a valid .hart file", path));
+                }
+                Ok(_) => {
+                    skip_lines -= 1;
+                    if skip_lines == 0 {
+                        break;
+                    } else {
+                        continue;
+                    }
+                }
+                Err(err) => {
+                    return Err(anyhow!(
+                        "The file {} is not a valid .hart file: {:?}",
+                        path,
+                        err
+                    ));
+                }
+            }
+        }
+        let decoder = XzDecoder::new(reader);
+        Ok(Archive::new(decoder))
+    }
+    /// Reads the entries of the package installation directory from the artifact,
+    /// unpacking them under the given root directory if one is provided.
+    async fn read_entries(&self, unpack_root: Option<PathBuf>) -> Result<Vec<ArtifactEntry>> {
+        let path = self.path.clone();
+        let install_dir = self.install_dir();
+        tokio::task::spawn_blocking(move || {
+            let install_dir = install_dir.strip_prefix(FS_ROOT.as_path())?;
+            let mut tar = PackageArtifact::open_archive(&path)?;
+            let mut artifact_entries = Vec::new();
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                let entry_path = entry.path()?.to_path_buf();
+                if let Some(unpack_root) = unpack_root.as_ref() {
+                    if entry.header().entry_type().is_dir() {
+                        // We don't apply directory permissions to ensure
+                        // we can always unpack the directory contents. Like
+                        // `unpack_in` does for files, paths that would escape
+                        // the unpack root are refused.
+                        if !entry_path.components().all(|component| {
+                            matches!(component, Component::Normal(_) | Component::CurDir)
+                        }) {
+                            return Err(anyhow!(
+                                "Refusing to unpack directory '{}' from artifact {} outside of '{}'",
+                                entry_path.display(),
+                                path,
+                                unpack_root.display()
+                            ));
+                        }
+                        std::fs::create_dir_all(unpack_root.join(entry_path.as_path()))?;
+                    } else {
+                        entry.unpack_in(unpack_root).with_context(|| {
+                            format!(
+                                "Failed to unpack '{}' from artifact {}",
+                                entry_path.display(),
+                                path
+                            )
+                        })?;
+                    }
+                }
+                let rel_path = match entry_path.strip_prefix(install_dir) {
+                    Ok(rel_path) if rel_path.components().count() > 0 => rel_path.to_path_buf(),
+                    _ => continue,
+                };
+                let header = entry.header();
+                let entry_type = if header.entry_type().is_dir() {
+                    ArtifactEntryType::Dir
+                } else if header.entry_type().is_symlink() {
+                    ArtifactEntryType::Symlink(
+                        entry
+                            .link_name()?
+                            .map(|p| p.to_path_buf())
+                            .unwrap_or_default(),
+                    )
+                } else if header.entry_type().is_file() || header.entry_type().is_hard_link() {
+                    ArtifactEntryType::File
+                } else {
+                    continue;
+                };
+                artifact_entries.push(ArtifactEntry {
+                    path: rel_path,
+                    entry_type,
+                    mode: header.mode()?,
+                    uid: header.uid()?,
+                    gid: header.gid()?,
+                    size: header.size()?,
+                });
+            }
+            Ok(artifact_entries)
+        })
+        .await?
+    }
     pub fn install_dir(&self) -> PathBuf {
         PathBuf::from(format!(
             "/hab/pkgs/{}/{}/{}/{}",
             self.ident.origin, self.ident.name, self.ident.version, self.ident.release
         ))
     }
-    pub async fn install(&self) -> Result<()> {
+    /// Reads the metafiles at the top of the package installation directory
+    /// from the artifact without unpacking it.
+    pub async fn read_metafiles(&self) -> Result<HashMap<String, String>> {
+        let path = self.path.clone();
+        let install_dir = self.install_dir();
+        tokio::task::spawn_blocking(move || {
+            let install_dir = install_dir.strip_prefix(FS_ROOT.as_path())?;
+            let mut tar = PackageArtifact::open_archive(&path)?;
+            let mut metafiles = HashMap::new();
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let entry_path = entry.path()?.to_path_buf();
+                let name = match entry_path.strip_prefix(install_dir) {
+                    Ok(rel_path) if rel_path.components().count() == 1 => {
+                        rel_path.to_string_lossy().to_string()
+                    }
+                    _ => continue,
+                };
+                if is_metafile_name(&name) {
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data)?;
+                    metafiles.insert(name, String::from_utf8_lossy(&data).to_string());
+                }
+            }
+            Ok(metafiles)
+        })
+        .await?
+    }
+    /// Unpacks the artifact under the given root directory
+    pub async fn unpack_in(&self, root: impl AsRef<Path>) -> Result<()> {
+        self.read_entries(Some(root.as_ref().to_path_buf())).await?;
+        Ok(())
+    }
+    /// Unpacks the artifact into a temporary root directory
+    pub async fn extract(&self) -> Result<PackageArtifactContents> {
+        let tmp_dir = TempDir::new("hab-auto-build-artifact")?;
+        debug!(
+            "Extracting package {} to {}",
+            self.ident,
+            tmp_dir.path().display()
+        );
+        let entries = self
+            .read_entries(Some(tmp_dir.path().to_path_buf()))
+            .await?;
+        Ok(PackageArtifactContents {
+            root: tmp_dir.path().to_path_buf(),
+            install_dir: tmp_dir
+                .path()
+                .join(self.install_dir().strip_prefix(FS_ROOT.as_path())?),
+            entries,
+            tmp_dir: Some(tmp_dir),
+        })
+    }
+    pub async fn install(&self) -> Result<PackageArtifactContents> {
         debug!("Installing package from {}", self.ident);
         let mut system_hab = SYSTEM_HABITAT.write().await;
         system_hab.pkg_install(self.path.borrow().into()).await?;
+        Ok(PackageArtifactContents {
+            root: FS_ROOT.clone(),
+            install_dir: self.install_dir(),
+            entries: self.read_entries(None).await?,
+            tmp_dir: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ArtifactEntryType {
+    Dir,
+    File,
+    Symlink(PathBuf),
+}
+
+/// An entry within the package installation directory of an artifact
+#[derive(Debug, Clone)]
+pub struct ArtifactEntry {
+    pub path: PathBuf,
+    pub entry_type: ArtifactEntryType,
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+    pub size: u64,
+}
+
+/// The contents of an artifact available on the file system, either installed
+/// on the system or unpacked into a temporary root directory.
+#[derive(Debug)]
+pub struct PackageArtifactContents {
+    pub root: PathBuf,
+    pub install_dir: PathBuf,
+    pub entries: Vec<ArtifactEntry>,
+    tmp_dir: Option<TempDir>,
+}
+
+impl PackageArtifactContents {
+    /// Unpacks the artifacts of all transitive runtime dependencies available in the
+    /// artifact cache so that they can be resolved without being installed.
+    pub async fn unpack_deps(
+        &self,
+        metadata: &PackageMetadata,
+        target: PackageTarget,
+    ) -> Result<()> {
+        if self.tmp_dir.is_none() {
+            return Ok(());
+        }
+        // Libraries are resolved through the whole closure, the TDEPS metafile lists it
+        let mut deps = PackageMetadata::read_deps(self.install_dir.join("TDEPS")).await;
+        deps.extend(metadata.deps.iter().cloned());
+        for dep in deps.iter() {
+            let dep_artifact_path = HAB_CACHE_ARTIFACTS_PATH.join(dep.artifact(target).to_string());
+            if let Ok(dep_artifact_path) = ValidFilePath::new(dep_artifact_path.as_path()).await {
+                let dep_artifact = PackageArtifact::new(&dep_artifact_path).await?;
+                dep_artifact.unpack_in(self.root.as_path()).await?;
+            } else {
+                debug!(
+                    "No artifact found for dependency {} at {}, it will be resolved from the system",
+                    dep,
+                    dep_artifact_path.display()
+                );
+            }
+        }
         Ok(())
     }
+    /// Resolves an absolute path to the location within the root directory if it
+    /// exists, otherwise to the location on the system.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        resolve_in_root(self.root.as_path(), path)
+    }
+}
+
+pub fn resolve_in_root(root: impl AsRef<Path>, path: impl AsRef<Path>) -> PathBuf {
+    if let Ok(rel_path) = path.as_ref().strip_prefix(FS_ROOT.as_path()) {
+        let root_path = root.as_ref().join(rel_path);
+        if root_path.exists() || root_path.is_symlink() {
+            return root_path;
+        }
+    }
+    path.as_ref().to_path_buf()
 }
 
 #[derive(Debug, Clone)]
@@ -264,6 +497,8 @@ pub struct PackageMetadata {
     pub deps: HashSet<PackageIdent>,
     pub build_deps: HashSet<PackageIdent>,
     pub pkg_config_path: Option<PathBuf>,
+    /// The package's `pkg_bin_dirs` from the `BIN_PATH` metafile
+    pub bin_dirs: Vec<PathBuf>,
     pub pkg_type: PackageType,
 }
 
@@ -276,35 +511,40 @@ impl PackageMetadata {
                 install_dir.as_ref().display()
             ));
         }
-        let ident = fs::read_to_string(install_dir.as_ref().join("IDENT"))
-            .await
-            .context("Package IDENT metafile not found")?;
-        let ident = PackageIdent::try_from(ident.as_str())
-            .with_context(|| format!("Invalid package identifier in IDENT metafile: {}", ident))?;
-        let deps = fs::read_to_string(install_dir.as_ref().join("DEPS"))
+        // A missing or malformed IDENT metafile is reported by the metafile check
+        let ident = match fs::read_to_string(install_dir.as_ref().join("IDENT")).await {
+            Ok(ident) => match PackageIdent::try_from(ident.as_str()) {
+                Ok(ident) => Some(ident),
+                Err(err) => {
+                    debug!("Invalid package identifier in IDENT metafile: {}", err);
+                    None
+                }
+            },
+            Err(err) => {
+                debug!(
+                    "Package IDENT metafile not found in {}: {}",
+                    install_dir.as_ref().display(),
+                    err
+                );
+                None
+            }
+        };
+        let deps = PackageMetadata::read_deps(install_dir.as_ref().join("DEPS")).await;
+        let build_deps = PackageMetadata::read_deps(install_dir.as_ref().join("BUILD_DEPS")).await;
+        let pkg_config_path = fs::read_to_string(install_dir.as_ref().join("PKG_CONFIG_PATH"))
             .await
             .ok()
-            .map(|data| {
-                data.lines()
-                    .map(|dep| PackageIdent::try_from(dep))
-                    .collect::<Result<HashSet<_>>>()
-                    .unwrap_or_default()
-            })
-            .unwrap_or_default();
-        let build_deps = fs::read_to_string(install_dir.as_ref().join("BUILD_DEPS"))
+            .map(PathBuf::from);
+        let bin_dirs = fs::read_to_string(install_dir.as_ref().join("BIN_PATH"))
             .await
-            .ok()
             .map(|data| {
-                data.lines()
-                    .map(|dep| PackageIdent::try_from(dep))
-                    .collect::<Result<HashSet<_>>>()
-                    .unwrap_or_default()
+                data.trim()
+                    .split(':')
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
             })
             .unwrap_or_default();
-        let pkg_config_path = fs::read_to_string(install_dir.as_ref().join("PKG_CONFIG_PATH"))
-            .await
-            .ok()
-            .map(PathBuf::from);
 
         let pkg_type = PackageType::try_from(
             fs::read_to_string(install_dir.as_ref().join("PACKAGE_TYPE"))
@@ -314,13 +554,35 @@ impl PackageMetadata {
         )?;
 
         Ok(PackageMetadata {
-            ident: Some(ident),
+            ident,
             deps,
             build_deps,
             pkg_config_path,
+            bin_dirs,
             pkg_type,
         })
     }
+    /// Reads a dependency metafile, malformed entries are skipped here and reported
+    /// by the metafile check.
+    async fn read_deps(path: impl AsRef<Path>) -> HashSet<PackageIdent> {
+        let Ok(data) = fs::read_to_string(path.as_ref()).await else {
+            return HashSet::new();
+        };
+        data.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|dep| match PackageIdent::try_from(dep) {
+                Ok(dep) => Some(dep),
+                Err(err) => {
+                    debug!(
+                        "Skipping dependency in {}: {}",
+                        path.as_ref().display(),
+                        err
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
     pub fn all_runtime_deps(&self) -> impl Iterator<Item = &PackageIdent> {
         self.ident.iter().chain(self.deps.iter())
     }
@@ -542,11 +804,21 @@ const HAB_AUTO_BUILD_EXTRACT_SOURCE_FILES: [(&str, &[u8]); 2] = [
     ("cache_index.sh", include_bytes!("./scripts/cache_index.sh")),
 ];
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 struct HabitatAutoBuildConfiguration {
     pub bootstrap_studio_package: Option<PackageDepIdent>,
     pub studio_package: Option<PackageDepIdent>,
     pub repos: Vec<RepoConfiguration>,
+    #[serde(default)]
+    pub checks: Vec<CheckConfiguration>,
+    #[serde(default)]
+    pub sources: SourceConfiguration,
+    /// Host interpreters that scripts in standard packages may use, such as `/bin/sh`
+    #[serde(default)]
+    pub platform_shells: Option<Vec<PathBuf>>,
+    /// Changes from the previous release of a package that are reported after building it
+    #[serde(default)]
+    pub regression: RegressionThresholds,
     #[serde(skip)]
     pub config_path: PathBuf,
 }
@@ -588,6 +860,12 @@ enum Commands {
     Server(ServerArgs),
     /// Check a habitat artifact for packaging issues
     Check(CheckArgs),
+    /// Compare dependencies between two git revisions
+    Diff(DiffArgs),
+    /// Analyze dependency cycles between packages
+    Cycles(CyclesArgs),
+    /// Generate a software bill of materials for a habitat artifact
+    Sbom(SbomArgs),
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -597,6 +875,39 @@ enum DependencyAnalysis {
     Reverse,
 }
 
+impl DependencyAnalysis {
+    /// Returns the set of packages selected by the analysis for the given packages
+    fn select(
+        &self,
+        dep_graph: &PackageDependencyGraph,
+        package_nodes: &[PackageNode],
+        dependency_types: &[DependencyType],
+    ) -> HashSet<NodeIndex> {
+        let mut selected = HashSet::new();
+        for package_node in package_nodes.iter() {
+            selected.extend(match self {
+                DependencyAnalysis::Build => package_node.build_closure(dep_graph),
+                DependencyAnalysis::Runtime => package_node.runtime_closure(dep_graph),
+                DependencyAnalysis::Reverse => {
+                    if dependency_types.is_empty() {
+                        package_node.reverse_closure(
+                            dep_graph,
+                            &[
+                                DependencyType::Runtime,
+                                DependencyType::Build,
+                                DependencyType::Studio,
+                            ],
+                        )
+                    } else {
+                        package_node.reverse_closure(dep_graph, dependency_types)
+                    }
+                }
+            });
+        }
+        selected
+    }
+}
+
 #[derive(Debug, Args)]
 struct ServerArgs {
     /// Path to hab auto build configuration
@@ -616,6 +927,47 @@ struct CheckArgs {
     /// Only print the summary of issues
     #[arg(short = 's', long)]
     only_summary: bool,
+    /// Install artifacts with sudo and check the installed packages instead of the artifact contents
+    #[arg(short = 'i', long)]
+    install_artifacts: bool,
+    /// List the available checks and exit
+    #[arg(long)]
+    list_checks: bool,
+    /// Only run the given checks
+    #[arg(long, value_name = "CHECK_ID")]
+    only: Vec<String>,
+    /// Skip the given checks
+    #[arg(long, value_name = "CHECK_ID")]
+    skip: Vec<String>,
+    /// Number of artifacts to check concurrently
+    #[arg(short, long, default_value_t = 1)]
+    jobs: usize,
+    /// Never download package sources, only use the source cache and mirror
+    #[arg(long)]
+    offline: bool,
+    /// Format of the check reports, json reports are printed one per line
+    #[arg(value_enum, short, long, default_value = "text")]
+    format: ReportFormat,
+}
+#[derive(Debug, Args)]
+struct SbomArgs {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Package or path to habitat artifact to generate the bill of materials for
+    package: String,
+    /// Format of the bill of materials
+    #[arg(value_enum, short, long, default_value = "spdx")]
+    format: SbomFormat,
+    /// Bill of materials output file, printed to stdout by default
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Don't scan package sources for licenses
+    #[arg(long)]
+    no_license_scan: bool,
+    /// Never download package sources, only use the source cache and mirror
+    #[arg(long)]
+    offline: bool,
 }
 #[derive(Debug, Args)]
 struct AnalyzeArgs {
@@ -623,8 +975,19 @@ struct AnalyzeArgs {
     #[arg(short, long)]
     config_path: Option<PathBuf>,
     /// Type of dependencies to analyze
-    #[arg(value_enum, short = 't', long)]
-    analysis_type: DependencyAnalysis,
+    #[arg(
+        value_enum,
+        short = 't',
+        long,
+        required_unless_present = "file_conflicts"
+    )]
+    analysis_type: Option<DependencyAnalysis>,
+    /// List the files shipped by more than one package in the runtime closure of the latest artifacts
+    #[arg(long, conflicts_with_all = ["analysis_type", "edge_types"])]
+    file_conflicts: bool,
+    /// Types of dependency edges to follow for reverse dependencies, defaults to all
+    #[arg(value_enum, short, long)]
+    edge_types: Vec<DependencyType>,
     /// List of packages to analyze
     #[arg(short, long)]
     packages: Vec<String>,
@@ -633,6 +996,36 @@ struct AnalyzeArgs {
     output: Option<PathBuf>,
 }
 
+#[derive(Debug, Args)]
+struct CyclesArgs {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// Include dependencies on the studio packages
+    #[arg(short = 's', long)]
+    include_studios: bool,
+    /// Cycle report output file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct DiffArgs {
+    /// Path to hab auto build configuration
+    #[arg(short, long)]
+    config_path: Option<PathBuf>,
+    /// List of packages whose dependency closures should be compared
+    #[arg(short, long)]
+    packages: Vec<String>,
+    /// Diff output file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Base git revision
+    rev_a: String,
+    /// Git revision to compare against the base revision
+    rev_b: String,
+}
+
 #[derive(Debug, Args)]
 struct VisualizeArgs {
     /// Path to hab auto build configuration
@@ -641,9 +1034,18 @@ struct VisualizeArgs {
     /// Type of dependencies to visualize
     #[arg(value_enum, short = 't', long)]
     analysis_type: DependencyAnalysis,
+    /// Types of dependency edges to follow for reverse dependencies, defaults to all
+    #[arg(value_enum, short, long)]
+    edge_types: Vec<DependencyType>,
     /// List of packages to visualize
     #[arg(short, long)]
     packages: Vec<String>,
+    /// Format of the dependency graph
+    #[arg(value_enum, short, long, default_value = "dot")]
+    format: GraphFormat,
+    /// Group packages into clusters
+    #[arg(value_enum, long)]
+    cluster: Option<GraphClustering>,
     /// Dependency graph output file
     #[arg(short, long)]
     output: PathBuf,
@@ -666,6 +1068,12 @@ struct BuildArgs {
     /// Maximum number of parallel build workers
     #[arg(short, long)]
     workers: Option<usize>,
+    /// Consider plans with changes since the given git ref as updated
+    #[arg(long)]
+    since: Option<String>,
+    /// Write a software bill of materials into the build folder after each successful build
+    #[arg(value_enum, long, value_name = "FORMAT")]
+    sbom: Option<SbomFormat>,
     /// List of updated plans
     updated_packages: Vec<String>,
 }
@@ -804,7 +1212,7 @@ impl From<PackageType> for String {
     }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(try_from = "String", into = "String")]
 pub enum PackageStudioType {
     Native,
@@ -1687,7 +2095,7 @@ impl Scripts {
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
 pub enum DependencyType {
     Runtime,
     Build,
@@ -1748,6 +2156,57 @@ impl PackageNode {
     ) -> bool {
         algo::has_path_connecting(&graph.0, self.0, other.0, None)
     }
+    /// Returns the package along with all packages reachable from it by following
+    /// edges of the given dependency types in the given direction.
+    pub fn closure(
+        &self,
+        graph: &PackageDependencyGraph,
+        direction: Direction,
+        dependency_types: &[DependencyType],
+    ) -> HashSet<NodeIndex> {
+        let mut closure = HashSet::new();
+        let mut next_nodes = VecDeque::new();
+        next_nodes.push_back(self.0);
+        while let Some(node) = next_nodes.pop_front() {
+            if !closure.insert(node) {
+                continue;
+            }
+            for edge in graph.0.edges_directed(node, direction) {
+                if dependency_types.contains(edge.weight()) {
+                    next_nodes.push_back(match direction {
+                        Direction::Outgoing => edge.target(),
+                        Direction::Incoming => edge.source(),
+                    });
+                }
+            }
+        }
+        closure
+    }
+    /// Returns the package along with its transitive runtime dependencies
+    pub fn runtime_closure(&self, graph: &PackageDependencyGraph) -> HashSet<NodeIndex> {
+        self.closure(graph, Direction::Outgoing, &[DependencyType::Runtime])
+    }
+    /// Returns the package along with its build dependencies and their
+    /// transitive runtime dependencies
+    pub fn build_closure(&self, graph: &PackageDependencyGraph) -> HashSet<NodeIndex> {
+        let mut closure = HashSet::new();
+        closure.insert(self.0);
+        for edge in graph.0.edges_directed(self.0, Direction::Outgoing) {
+            if matches!(edge.weight(), DependencyType::Build) {
+                closure.extend(PackageNode(edge.target()).runtime_closure(graph));
+            }
+        }
+        closure
+    }
+    /// Returns the package along with all packages that depend on it through
+    /// edges of the given dependency types
+    pub fn reverse_closure(
+        &self,
+        graph: &PackageDependencyGraph,
+        dependency_types: &[DependencyType],
+    ) -> HashSet<NodeIndex> {
+        self.closure(graph, Direction::Incoming, dependency_types)
+    }
 }
 
 impl Deref for PackageNode {
@@ -1767,6 +2226,7 @@ pub enum UpdateCause {
     UpdatedSource,
     UpdatedDependency,
     NoArtifact,
+    GitChange,
 }
 
 impl Display for UpdateCause {
@@ -1775,6 +2235,7 @@ impl Display for UpdateCause {
             UpdateCause::UpdatedSource => write!(f, "updated source"),
             UpdateCause::UpdatedDependency => write!(f, "updated dependency"),
             UpdateCause::NoArtifact => write!(f, "no build artifact"),
+            UpdateCause::GitChange => write!(f, "git change"),
         }
     }
 }
@@ -2018,41 +2479,15 @@ async fn visualize(args: VisualizeArgs) -> Result<()> {
     )
     .await?;
 
-    let output = {
-        if selected_package_nodes.is_empty() {
-            format!(
-                "{:?}",
-                Dot::with_config(&*dep_graph, &[Config::EdgeNoLabel])
-            )
-        } else {
-            let build_graph = NodeFiltered::from_fn(&*dep_graph, |node| {
-                let node = PackageNode(node);
-                let mut include = false;
-                for selected_package_node in selected_package_nodes.iter() {
-                    match args.analysis_type {
-                        DependencyAnalysis::Build | DependencyAnalysis::Runtime => {
-                            if node.is_dependency_of(&dep_graph, selected_package_node) {
-                                include = true;
-                                break;
-                            }
-                        }
-                        DependencyAnalysis::Reverse => {
-                            if selected_package_node.is_dependency_of(&dep_graph, &node) {
-                                include = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-                include
-            });
-            format!(
-                "{:?}",
-                Dot::with_config(&build_graph, &[Config::EdgeNoLabel])
-            )
-        }
+    let nodes = if selected_package_nodes.is_empty() {
+        dep_graph.node_indices().collect::<HashSet<_>>()
+    } else {
+        args.analysis_type
+            .select(&dep_graph, &selected_package_nodes, &args.edge_types)
     };
-    let output = output.replace("digraph {", "digraph { rankdir=LR; node [shape=rectangle, color=blue, fillcolor=lightskyblue, style=filled ]; edge [color=darkgreen];");
+    let output = GraphExport::new(&dep_graph, &nodes, args.cluster)
+        .render(args.format)
+        .await?;
     let mut output_file = tokio::fs::File::create(args.output).await?;
     output_file.write_all(output.as_bytes()).await?;
     output_file.shutdown().await?;
@@ -2081,42 +2516,48 @@ async fn analyze(args: AnalyzeArgs) -> Result<()> {
         false,
         true,
         None,
-        scripts,
+        scripts.clone(),
     )
     .await?;
 
-    let packages = if selected_package_nodes.is_empty() {
+    let packages = if args.file_conflicts {
+        let mut selected_nodes = if selected_package_nodes.is_empty() {
+            dep_graph.node_indices().collect::<Vec<_>>()
+        } else {
+            selected_package_nodes
+                .iter()
+                .map(|package_node| package_node.0)
+                .collect::<Vec<_>>()
+        };
+        selected_nodes.sort();
+
+        let mut conflicts = Vec::new();
+        for node in selected_nodes {
+            let plan_ident = &dep_graph[node].plan.ident;
+            let dep_ident = PackageDepIdent::from(plan_ident);
+            conflicts
+                .extend(analyze_file_conflicts(&dep_ident, plan_ident.target, &scripts).await?);
+        }
+        conflicts
+    } else if selected_package_nodes.is_empty() {
         let mut packages = Vec::new();
         for (_, node) in dep_graph.node_references() {
             packages.push(format!("{}", node.plan.ident))
         }
         packages
     } else {
-        let build_graph = NodeFiltered::from_fn(&*dep_graph, |node| {
-            let node = PackageNode(node);
-            let mut include = false;
-            for selected_package_node in selected_package_nodes.iter() {
-                match args.analysis_type {
-                    DependencyAnalysis::Build | DependencyAnalysis::Runtime => {
-                        if node.is_dependency_of(&dep_graph, selected_package_node) {
-                            include = true;
-                            break;
-                        }
-                    }
-                    DependencyAnalysis::Reverse => {
-                        if selected_package_node.is_dependency_of(&dep_graph, &node) {
-                            include = true;
-                            break;
-                        }
-                    }
-                }
-            }
-            include
-        });
+        let analysis_type = args
+            .analysis_type
+            .ok_or_else(|| anyhow!("An analysis type is required"))?;
+        let mut selected_nodes = analysis_type
+            .select(&dep_graph, &selected_package_nodes, &args.edge_types)
+            .into_iter()
+            .collect::<Vec<_>>();
+        selected_nodes.sort();
 
         let mut packages = Vec::new();
-        for (_, node) in build_graph.node_references() {
-            packages.push(format!("{}", node.plan.ident))
+        for node in selected_nodes {
+            packages.push(format!("{}", dep_graph[node].plan.ident))
         }
         packages
     };
@@ -2135,6 +2576,138 @@ async fn analyze(args: AnalyzeArgs) -> Result<()> {
     Ok(())
 }
 
+/// Lists the files shipped by more than one package in the runtime closure of the
+/// latest artifact of the package, one line per file.
+async fn analyze_file_conflicts(
+    dep_ident: &PackageDepIdent,
+    target: PackageTarget,
+    scripts: &Scripts,
+) -> Result<Vec<String>> {
+    let Some(artifact) = dep_ident.latest_artifact(target, scripts).await? else {
+        warn!("No package artifact found for {}, skipping it", dep_ident);
+        return Ok(Vec::new());
+    };
+    let artifact_path =
+        ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(format!("{}", artifact))).await?;
+    let artifact = PackageArtifact::new(&artifact_path).await?;
+    let contents = artifact
+        .extract()
+        .await
+        .with_context(|| format!("Failed to extract artifact {}", artifact.path))?;
+    let metadata = PackageMetadata::new(contents.install_dir.as_path()).await?;
+    contents
+        .unpack_deps(&metadata, artifact.ident.target)
+        .await
+        .context("Failed to extract runtime dependencies")?;
+    Ok(
+        conflicts::find_file_conflicts(contents.root.as_path(), &metadata)
+            .await?
+            .into_iter()
+            .map(|conflict| {
+                format!(
+                    "{}: {} {}",
+                    PackageIdent::from(&artifact.ident),
+                    conflict.path.display(),
+                    conflict
+                        .packages
+                        .iter()
+                        .map(|package| package.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )
+            })
+            .collect(),
+    )
+}
+
+async fn diff(args: DiffArgs) -> Result<()> {
+    let scripts = Arc::new(Scripts::new().await?);
+    let selected_packages = args
+        .packages
+        .into_iter()
+        .map(PackageDepIdent::try_from)
+        .map(|ident| ident.map(|ident| format!("{}/{}", ident.origin, ident.name)))
+        .collect::<Result<Vec<String>, _>>()?;
+
+    let auto_build_config = HabitatAutoBuildConfiguration::new(
+        args.config_path
+            .unwrap_or(env::current_dir()?.join("hab-auto-build.json")),
+    )
+    .await
+    .context("Failed to load habitat auto build configuration")?;
+
+    let mut summaries = Vec::new();
+    for revision in [args.rev_a.as_str(), args.rev_b.as_str()] {
+        let worktree = diff::RevisionWorktree::new(revision, &auto_build_config).await?;
+        let summary = worktree.summary(scripts.clone()).await;
+        worktree.remove().await?;
+        summaries.push(summary.with_context(|| {
+            format!("Failed to build dependency graph at revision {}", revision)
+        })?);
+    }
+    let output = summaries[0].diff(&summaries[1], &selected_packages);
+
+    if let Some(output_file_path) = args.output {
+        let mut output_file = tokio::fs::File::create(output_file_path).await?;
+        output_file.write_all(output.as_bytes()).await?;
+        output_file.shutdown().await?;
+    } else {
+        print!("{}", output);
+    }
+    Ok(())
+}
+
+async fn cycles(args: CyclesArgs) -> Result<()> {
+    let scripts = Arc::new(Scripts::new().await?);
+    let auto_build_config = HabitatAutoBuildConfiguration::new(
+        args.config_path
+            .unwrap_or(env::current_dir()?.join("hab-auto-build.json")),
+    )
+    .await
+    .context("Failed to load habitat auto build configuration")?;
+
+    let (dep_graph, _, _, _) = dep_graph_build(
+        vec![],
+        &auto_build_config,
+        false,
+        args.include_studios,
+        None,
+        scripts,
+    )
+    .await?;
+
+    let cycles = DependencyCycle::detect(&dep_graph);
+    let mut output = String::new();
+    for (index, cycle) in cycles.iter().enumerate() {
+        output.push_str(&cycle.report(&dep_graph, index + 1));
+    }
+    let (runtime_cuts, build_cuts) = cycles
+        .iter()
+        .flat_map(|cycle| cycle.suggested_cuts.iter())
+        .fold(
+            (0, 0),
+            |(runtime, build), (_, _, dependency_type)| match dependency_type {
+                DependencyType::Runtime => (runtime + 1, build),
+                DependencyType::Build | DependencyType::Studio => (runtime, build + 1),
+            },
+        );
+    output.push_str(&format!(
+        "{} cycles found, {} runtime and {} build dependencies need to be broken\n",
+        cycles.len(),
+        runtime_cuts,
+        build_cuts
+    ));
+
+    if let Some(output_file_path) = args.output {
+        let mut output_file = tokio::fs::File::create(output_file_path).await?;
+        output_file.write_all(output.as_bytes()).await?;
+        output_file.shutdown().await?;
+    } else {
+        print!("{}", output);
+    }
+    Ok(())
+}
+
 async fn serve(args: ServerArgs) -> Result<()> {
     let scripts = Arc::new(Scripts::new().await?);
     let auto_build_config = HabitatAutoBuildConfiguration::new(
@@ -2149,9 +2722,75 @@ async fn serve(args: ServerArgs) -> Result<()> {
     Ok(())
 }
 
+/// Checks an artifact for packaging issues. Unless the artifact is installed on the system
+/// it is checked by unpacking it and its runtime dependencies into a temporary directory.
+async fn check_artifact(
+    artifact_path: &ValidFilePath,
+    fs_root: impl AsRef<Path>,
+    install_artifact: bool,
+    registry: &CheckRegistry,
+) -> Result<ArtifactReport> {
+    let artifact = PackageArtifact::new(artifact_path).await?;
+    let contents = if install_artifact {
+        artifact
+            .install()
+            .await
+            .with_context(|| format!("Failed to install artifact {}", artifact.path))?
+    } else {
+        artifact
+            .extract()
+            .await
+            .with_context(|| format!("Failed to extract artifact {}", artifact.path))?
+    };
+    let metadata = PackageMetadata::new(contents.install_dir.as_path()).await?;
+    contents
+        .unpack_deps(&metadata, artifact.ident.target)
+        .await
+        .context("Failed to extract runtime dependencies")?;
+    let mut checker =
+        ArtifactChecker::new(artifact, &contents, &metadata, fs_root, registry).await?;
+    checker.check().await.with_context(|| {
+        format!(
+            "There were issues while checking artifact {}",
+            artifact_path.as_ref().display()
+        )
+    })
+}
+
 async fn check(args: CheckArgs) -> Result<()> {
+    let mut registry = CheckRegistry::default();
+    if args.list_checks {
+        registry.print();
+        return Ok(());
+    }
     let scripts = Arc::new(Scripts::new().await?);
 
+    // The configuration is optional when checking a single package
+    let config_path = args
+        .config_path
+        .clone()
+        .unwrap_or(env::current_dir()?.join("hab-auto-build.json"));
+    let auto_build_config =
+        if args.package.is_none() || args.config_path.is_some() || config_path.exists() {
+            let auto_build_config = HabitatAutoBuildConfiguration::new(config_path)
+                .await
+                .context("Failed to load habitat auto build configuration")?;
+            registry.configure(&auto_build_config.checks)?;
+            if let Some(platform_shells) = auto_build_config.platform_shells.clone() {
+                registry.set_platform_shells(platform_shells);
+            }
+            Some(auto_build_config)
+        } else {
+            None
+        };
+    let mut sources = auto_build_config
+        .as_ref()
+        .map(|config| config.sources.clone())
+        .unwrap_or_default();
+    sources.offline |= args.offline;
+    registry.set_sources(sources);
+    registry.select(&args.only, &args.skip)?;
+
     if let Some(package) = args.package {
         let dep_ident = PackageDepIdent::try_from(package)?;
 
@@ -2162,29 +2801,17 @@ async fn check(args: CheckArgs) -> Result<()> {
         let artifact_path =
             ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(format!("{}", artifact))).await?;
 
-        let artifact = PackageArtifact::new(&artifact_path).await?;
-        artifact
-            .install()
-            .await
-            .with_context(|| format!("Failed to install artifact {}", artifact.path))?;
-        let metadata = PackageMetadata::new(artifact.install_dir()).await?;
-        let mut checker = ArtifactChecker::new(artifact, &metadata, FS_ROOT.as_path()).await?;
-        let report = checker.check().await.with_context(|| {
-            format!(
-                "There were issues while checking artifact {}",
-                artifact_path.as_ref().display()
-            )
-        })?;
-        report.print(args.only_summary);
+        let report = check_artifact(
+            &artifact_path,
+            FS_ROOT.as_path(),
+            args.install_artifacts,
+            &registry,
+        )
+        .await?;
+        report.print(args.format, args.only_summary)?;
         Ok(())
     } else {
-        let config_path = args
-            .config_path
-            .unwrap_or(env::current_dir()?.join("hab-auto-build.json"));
-
-        let auto_build_config = HabitatAutoBuildConfiguration::new(config_path)
-            .await
-            .context("Failed to load habitat auto build configuration")?;
+        let auto_build_config = auto_build_config.unwrap();
 
         let (dep_graph, _manually_updated_package_nodes, package_node_updates, _studio_packages) =
             dep_graph_build(
@@ -2210,34 +2837,166 @@ async fn check(args: CheckArgs) -> Result<()> {
         );
         info!("Checking {} packages", check_order.len());
 
-        for item in check_order {
+        // Artifacts are independent of each other so they can be checked in any order,
+        // the reports are still printed in the check order as they complete.
+        let registry = Arc::new(registry);
+        let mut reports = futures::stream::iter(check_order.into_iter().map(|item| {
             let dep_ident = PackageDepIdent::from(&dep_graph[item].plan.ident);
+            let scripts = scripts.clone();
+            let registry = registry.clone();
+            let install_artifacts = args.install_artifacts;
+            let handle = tokio::spawn({
+                let dep_ident = dep_ident.clone();
+                async move {
+                    let artifact = dep_ident
+                        .latest_artifact(PackageTarget::default(), &scripts)
+                        .await?
+                        .ok_or_else(|| anyhow!("No package artifact found for {}", dep_ident))?;
+                    let artifact_path =
+                        ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(format!("{}", artifact)))
+                            .await?;
+                    check_artifact(
+                        &artifact_path,
+                        FS_ROOT.as_path(),
+                        install_artifacts,
+                        &registry,
+                    )
+                    .await
+                }
+            });
+            async move { (dep_ident, handle.await) }
+        }))
+        .buffered(args.jobs.max(1));
+
+        let mut summary = CheckSummary::default();
+        while let Some((dep_ident, result)) = reports.next().await {
+            match result
+                .context("Check task panicked")
+                .and_then(|result| result)
+            {
+                Ok(report) => {
+                    report.print(args.format, args.only_summary)?;
+                    summary.add(&report);
+                }
+                Err(err) => {
+                    error!("Failed to check {}: {:#}", dep_ident, err);
+                    summary.add_failure(dep_ident.to_string(), err);
+                }
+            }
+        }
+        if matches!(args.format, ReportFormat::Text) {
+            summary.print();
+        }
 
-            let artifact = dep_ident
-                .latest_artifact(PackageTarget::default(), &scripts)
-                .await?
-                .ok_or_else(|| anyhow!("No package artifact found for {}", dep_ident))?;
-            let artifact_path =
-                ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(format!("{}", artifact))).await?;
+        Ok(())
+    }
+}
 
-            let artifact = PackageArtifact::new(&artifact_path).await?;
-            artifact
-                .install()
-                .await
-                .with_context(|| format!("Failed to install artifact {}", artifact.path))?;
-            let metadata = PackageMetadata::new(artifact.install_dir()).await?;
-            let mut checker = ArtifactChecker::new(artifact, &metadata, FS_ROOT.as_path()).await?;
-            let report = checker.check().await.with_context(|| {
-                format!(
-                    "There were issues while checking artifact {}",
-                    artifact_path.as_ref().display()
-                )
-            })?;
-            report.print(args.only_summary);
+/// Finds all packages whose plan folder or the files sourced by their plan
+/// have changed since the given git ref.
+async fn git_updated_package_nodes(
+    dep_graph: &PackageDependencyGraph,
+    git_ref: &str,
+) -> Result<Vec<PackageNode>> {
+    // Plans of the same repo share a git repository, so each one is only resolved once
+    let repos = dep_graph
+        .node_indices()
+        .map(|node| dep_graph[node].plan.repo.as_path())
+        .collect::<BTreeSet<_>>();
+    let mut git_changes = GitChanges::new(git_ref);
+    let mut changed_files = BTreeSet::new();
+    for repo in repos {
+        changed_files.extend(git_changes.changed_files(repo).await?.iter().cloned());
+    }
+    let changed_shared_files = changed_files
+        .iter()
+        .filter(|file| {
+            !dep_graph
+                .node_indices()
+                .any(|node| file.starts_with(dep_graph[node].plan.source.as_path()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut updated_package_nodes = Vec::new();
+    for node in dep_graph.node_indices() {
+        let plan = &dep_graph[node].plan;
+        if let Some(changed_file) = changed_files
+            .iter()
+            .find(|file| file.starts_with(plan.source.as_path()))
+        {
+            debug!(
+                "Package {} has a file {} that changed since {}",
+                plan.ident,
+                changed_file.display(),
+                git_ref
+            );
+            updated_package_nodes.push(PackageNode(node));
+            continue;
+        }
+        if changed_shared_files.is_empty() {
+            continue;
+        }
+        for sourced_file in plan_sourced_files(plan.path.as_path()).await? {
+            if let Some(changed_file) = changed_shared_files
+                .iter()
+                .find(|file| sourced_file_matches(sourced_file.as_path(), file))
+            {
+                debug!(
+                    "Package {} sources a file {} that changed since {}",
+                    plan.ident,
+                    changed_file.display(),
+                    git_ref
+                );
+                updated_package_nodes.push(PackageNode(node));
+                break;
+            }
         }
+    }
+    Ok(updated_package_nodes)
+}
 
-        Ok(())
+async fn sbom(args: SbomArgs) -> Result<()> {
+    let scripts = Scripts::new().await?;
+    let config_path = args
+        .config_path
+        .clone()
+        .unwrap_or(env::current_dir()?.join("hab-auto-build.json"));
+    let mut sources = if args.config_path.is_some() || config_path.exists() {
+        HabitatAutoBuildConfiguration::new(config_path)
+            .await
+            .context("Failed to load habitat auto build configuration")?
+            .sources
+    } else {
+        SourceConfiguration::default()
+    };
+    sources.offline |= args.offline;
+
+    let artifact_path = if args.package.ends_with(".hart") {
+        ValidFilePath::new(args.package.as_str()).await?
+    } else {
+        let dep_ident = PackageDepIdent::try_from(args.package)?;
+        let artifact = dep_ident
+            .latest_artifact(PackageTarget::default(), &scripts)
+            .await?
+            .ok_or_else(|| anyhow!("No package artifact found for {}", dep_ident))?;
+        ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(format!("{}", artifact))).await?
+    };
+    let artifact = PackageArtifact::new(&artifact_path).await?;
+    let license_check = if args.no_license_scan {
+        None
+    } else {
+        Some(LicenseCheck::new(FS_ROOT.as_path(), sources)?)
+    };
+    let sbom = Sbom::new(&artifact, license_check.as_ref()).await?;
+    let output = sbom.render(args.format)?;
+
+    if let Some(output_file_path) = args.output {
+        let mut output_file = tokio::fs::File::create(output_file_path).await?;
+        output_file.write_all(output.as_bytes()).await?;
+    } else {
+        println!("{}", output);
     }
+    Ok(())
 }
 
 async fn build(args: BuildArgs) -> Result<()> {
@@ -2259,6 +3018,13 @@ async fn build(args: BuildArgs) -> Result<()> {
     let auto_build_config = HabitatAutoBuildConfiguration::new(config_path)
         .await
         .context("Failed to load habitat auto build configuration")?;
+    let mut check_registry = CheckRegistry::default();
+    check_registry.configure(&auto_build_config.checks)?;
+    check_registry.set_sources(auto_build_config.sources.clone());
+    if let Some(platform_shells) = auto_build_config.platform_shells.clone() {
+        check_registry.set_platform_shells(platform_shells);
+    }
+    check_registry.set_regression_thresholds(auto_build_config.regression.clone());
 
     let package_skip_list = PackageSkipList::new(package_skip_path).await.ok();
 
@@ -2273,6 +3039,20 @@ async fn build(args: BuildArgs) -> Result<()> {
         )
         .await?;
 
+    if let Some(git_ref) = args.since.as_ref() {
+        for package_node in git_updated_package_nodes(&dep_graph, git_ref).await? {
+            if !package_node_updates
+                .iter()
+                .any(|package_node_update| package_node_update.package == package_node)
+            {
+                package_node_updates.push(PackageNodeUpdate {
+                    package: package_node,
+                    cause: UpdateCause::GitChange,
+                });
+            }
+        }
+    }
+
     for package_node_update in package_node_updates.iter() {
         info!(
             "Detected update due to {} in {} at {}",
@@ -2559,7 +3339,9 @@ async fn build(args: BuildArgs) -> Result<()> {
         auto_build_config.bootstrap_studio_package,
         auto_build_config.studio_package,
         scripts,
-    );
+        Arc::new(check_registry),
+    )
+    .with_sbom_format(args.sbom);
 
     info!(
         "Beginning build {}, {} packages to be built",
@@ -2592,6 +3374,9 @@ async fn main() -> Result<()> {
         Commands::Analyze(args) => analyze(args).await,
         Commands::Server(args) => serve(args).await,
         Commands::Check(args) => check(args).await,
+        Commands::Diff(args) => diff(args).await,
+        Commands::Cycles(args) => cycles(args).await,
+        Commands::Sbom(args) => sbom(args).await,
     }
 }
 
@@ -2605,6 +3390,8 @@ struct Scheduler {
     build_order: Arc<Vec<NodeIndex>>,
     origin_keys: BTreeSet<String>,
     dep_graph: Arc<PackageDependencyGraph>,
+    check_registry: Arc<CheckRegistry>,
+    sbom_format: Option<SbomFormat>,
     handles: FuturesUnordered<JoinHandle<Result<(), anyhow::Error>>>,
 }
 
@@ -2612,14 +3399,21 @@ struct PackageBuilder<'a> {
     session_id: String,
     worker_index: usize,
     build: &'a PackageBuild,
+    sbom_format: Option<SbomFormat>,
 }
 
 impl<'a> PackageBuilder<'a> {
-    fn new(session_id: &str, worker_index: usize, build: &'a PackageBuild) -> PackageBuilder<'a> {
+    fn new(
+        session_id: &str,
+        worker_index: usize,
+        build: &'a PackageBuild,
+        sbom_format: Option<SbomFormat>,
+    ) -> PackageBuilder<'a> {
         PackageBuilder {
             session_id: session_id.to_owned(),
             worker_index,
             build,
+            sbom_format,
         }
     }
     async fn build(
@@ -2629,11 +3423,13 @@ impl<'a> PackageBuilder<'a> {
         bootstrap_studio_package: Option<PackageDepIdent>,
         studio_package: Option<PackageDepIdent>,
         scripts: Arc<Scripts>,
+        check_registry: Arc<CheckRegistry>,
     ) -> Result<()> {
         let PackageBuilder {
             session_id,
             worker_index,
             build,
+            sbom_format,
         } = self;
         info!(
             worker = worker_index,
@@ -2935,7 +3731,7 @@ impl<'a> PackageBuilder<'a> {
                                     }
                                 }
 
-                                // Install and check the package after building it
+                                // Check the package after building it
                                 let dep_ident = PackageDepIdent::from(&build.plan.ident);
                                 let artifact = dep_ident
                                     .latest_artifact(build.plan.ident.target, &scripts)
@@ -2944,21 +3740,23 @@ impl<'a> PackageBuilder<'a> {
                                 let artifact_path =
                                     ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(format!("{}", artifact))).await?;
 
-                                let artifact = PackageArtifact::new(&artifact_path).await?;
-                                artifact
-                                    .install()
-                                    .await
-                                    .with_context(|| format!("Failed to install artifact {}", artifact.path))?;
-                                let metadata = PackageMetadata::new(artifact.install_dir()).await?;
-                                let mut checker = ArtifactChecker::new(artifact, &metadata, fs_root).await?;
                                 info!("Verifying package artifact {}", artifact_path.as_ref().display());
-                                let report = checker.check().await.with_context(|| {
-                                    format!(
-                                        "There were issues while checking artifact {}",
-                                        artifact_path.as_ref().display()
-                                    )
-                                })?;
-                                report.print(false);
+                                // Later builds resolve their deps from the installed packages so the
+                                // freshly built package is installed before it is checked
+                                let report = check_artifact(&artifact_path, fs_root.as_path(), true, &check_registry).await?;
+                                report.print(ReportFormat::Text, false)?;
+
+                                if let Some(sbom_format) = sbom_format {
+                                    // Sources were just downloaded by the build so the license scan never needs the network
+                                    let mut sources = check_registry.sources().clone();
+                                    sources.offline = true;
+                                    let license_check = LicenseCheck::new(fs_root.as_path(), sources)?;
+                                    let artifact = PackageArtifact::new(&artifact_path).await?;
+                                    let sbom_path = build.package_build_folder(&session_id).join(sbom_format.file_name());
+                                    let sbom = Sbom::new(&artifact, Some(&license_check)).await?;
+                                    tokio::fs::write(&sbom_path, sbom.render(sbom_format)?).await.with_context(|| format!("Failed to write software bill of materials to {}", sbom_path.display()))?;
+                                    info!(worker = worker_index, "Wrote software bill of materials to {}", sbom_path.display());
+                                }
                                 return Ok(())
                             } else {
                                 error!(worker = worker_index, "Failed to build {:?}, build process exited with {}, please the build log for errors: {}", build.plan, exit_code, build.build_log_file(&session_id).display());
@@ -2987,6 +3785,7 @@ impl Scheduler {
         bootstrap_studio_package: Option<PackageDepIdent>,
         studio_package: Option<PackageDepIdent>,
         scripts: Arc<Scripts>,
+        check_registry: Arc<CheckRegistry>,
     ) -> Scheduler {
         let mut origin_keys = BTreeSet::new();
         for package_index in build_order.iter() {
@@ -3004,9 +3803,16 @@ impl Scheduler {
             build_order,
             origin_keys,
             dep_graph,
+            check_registry,
+            sbom_format: None,
             handles: FuturesUnordered::new(),
         }
     }
+    /// Writes a software bill of materials of the given format for every package built
+    pub fn with_sbom_format(mut self, sbom_format: Option<SbomFormat>) -> Scheduler {
+        self.sbom_format = sbom_format;
+        self
+    }
     fn mark_complete(built_packages: Arc<DashSet<NodeIndex>>, package_index: NodeIndex) {
         built_packages.insert(package_index);
     }
@@ -3055,6 +3861,8 @@ impl Scheduler {
             let bootstrap_studio_package = self.bootstrap_studio_package.clone();
             let studio_package = self.studio_package.clone();
             let origin_keys = self.origin_keys.clone();
+            let check_registry = self.check_registry.clone();
+            let sbom_format = self.sbom_format;
             async move {
                 loop {
                     match Scheduler::next(
@@ -3065,7 +3873,8 @@ impl Scheduler {
                     ) {
                         NextPackageBuild::Ready(package_index) => {
                             let build = &dep_graph[package_index];
-                            let builder = PackageBuilder::new(&session_id, worker_index, build);
+                            let builder =
+                                PackageBuilder::new(&session_id, worker_index, build, sbom_format);
                             let build_deps = dep_graph
                                 .neighbors_directed(package_index, Direction::Outgoing)
                                 .into_iter()
@@ -3078,6 +3887,7 @@ impl Scheduler {
                                     bootstrap_studio_package.clone(),
                                     studio_package.clone(),
                                     scripts.clone(),
+                                    check_registry.clone(),
                                 )
                                 .await?;
                             Scheduler::mark_complete(built_packages.clone(), package_index);
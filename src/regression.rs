@@ -0,0 +1,339 @@
+use anyhow::Result;
+use goblin::elf::{
+    section_header::SHN_UNDEF,
+    sym::{STB_GLOBAL, STB_WEAK, STT_FUNC, STT_GNU_IFUNC, STT_OBJECT, STT_TLS, STV_DEFAULT},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Read,
+    path::PathBuf,
+};
+
+use crate::{
+    cache_index, checker::Finding, is_metafile_name, PackageArtifact, PackageArtifactIdent,
+    PackageIdent, ValidFilePath, FS_ROOT, HAB_CACHE_ARTIFACTS_PATH,
+};
+
+/// Number of file names or symbols listed in a single warning
+const MAX_LISTED_ITEMS: usize = 5;
+
+/// Limits beyond which changes from the previous release of a package are reported
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegressionThresholds {
+    /// Change of the total size of the package files in percent
+    pub size_change: f64,
+    /// Change of the number of package files in percent
+    pub file_count_change: f64,
+    /// Files removed since the previous release in percent of its files, removed
+    /// ELF objects and shared libraries are always reported
+    pub files_removed: f64,
+    /// Files added since the previous release in percent of its files
+    pub files_added: f64,
+}
+
+impl Default for RegressionThresholds {
+    fn default() -> Self {
+        RegressionThresholds {
+            size_change: 30.0,
+            file_count_change: 30.0,
+            files_removed: 10.0,
+            files_added: 10.0,
+        }
+    }
+}
+
+/// The dynamic section of an ELF object that matters to the objects linking against it
+#[derive(Debug, Default)]
+struct ElfSummary {
+    needed: BTreeSet<String>,
+    exports: BTreeSet<String>,
+}
+
+/// The contents of an artifact that are compared between releases
+#[derive(Debug)]
+pub struct ArtifactSummary {
+    ident: PackageIdent,
+    total_size: u64,
+    files: BTreeSet<PathBuf>,
+    objects: BTreeMap<PathBuf, ElfSummary>,
+}
+
+impl ArtifactSummary {
+    /// Reads the artifact without unpacking it, only executables and files named like shared
+    /// libraries are parsed as ELF objects.
+    pub async fn new(artifact: &PackageArtifact) -> Result<ArtifactSummary> {
+        let path = artifact.path.clone();
+        let ident = PackageIdent::from(&artifact.ident);
+        let install_dir = artifact.install_dir();
+        tokio::task::spawn_blocking(move || {
+            let install_dir = install_dir.strip_prefix(FS_ROOT.as_path())?;
+            let mut tar = PackageArtifact::open_archive(&path)?;
+            let mut total_size = 0;
+            let mut files = BTreeSet::new();
+            let mut objects = BTreeMap::new();
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                let entry_type = entry.header().entry_type();
+                if !(entry_type.is_file() || entry_type.is_symlink()) {
+                    continue;
+                }
+                let rel_path = match entry.path()?.strip_prefix(install_dir) {
+                    Ok(rel_path) => rel_path.to_path_buf(),
+                    Err(_) => continue,
+                };
+                // Metafiles such as IDENT and MANIFEST change with every release
+                if rel_path.components().count() == 1
+                    && is_metafile_name(&rel_path.to_string_lossy())
+                {
+                    continue;
+                }
+                files.insert(rel_path.clone());
+                if !entry_type.is_file() {
+                    continue;
+                }
+                let size = entry.header().size()?;
+                total_size += size;
+                let file_name = rel_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let is_executable = entry.header().mode()? & 0o111 != 0;
+                if !(is_executable || file_name.contains(".so")) {
+                    continue;
+                }
+                let mut data = Vec::with_capacity(size as usize);
+                entry.read_to_end(&mut data)?;
+                if let Some(object) = elf_summary(&data) {
+                    objects.insert(rel_path, object);
+                }
+            }
+            Ok(ArtifactSummary {
+                ident,
+                total_size,
+                files,
+                objects,
+            })
+        })
+        .await?
+    }
+
+    /// Compares the artifact with a previous release, returning a warning for each change
+    /// beyond the thresholds, for each removed ELF object or shared library and for each
+    /// change to the libraries the ELF objects need or the symbols shared libraries export.
+    pub fn compare(
+        &self,
+        previous: &ArtifactSummary,
+        thresholds: &RegressionThresholds,
+    ) -> Vec<Finding> {
+        let mut warnings = Vec::new();
+        if let Some(change) = percent_change(previous.total_size, self.total_size) {
+            if change.abs() > thresholds.size_change {
+                warnings.push(Finding::new(
+                    "SIZE",
+                    format!(
+                        "Package size changed by {:+.1}% from {} bytes in {} to {} bytes",
+                        change, previous.total_size, previous.ident, self.total_size
+                    ),
+                ));
+            }
+        }
+        if let Some(change) = percent_change(previous.files.len() as u64, self.files.len() as u64) {
+            if change.abs() > thresholds.file_count_change {
+                warnings.push(Finding::new(
+                    "FILE_COUNT",
+                    format!(
+                        "Package file count changed by {:+.1}% from {} files in {} to {} files",
+                        change,
+                        previous.files.len(),
+                        previous.ident,
+                        self.files.len()
+                    ),
+                ));
+            }
+        }
+
+        let mut removed_files = Vec::new();
+        for path in previous.files.difference(&self.files) {
+            // Shared libraries are usually symlinks named after their soname
+            let is_library = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().contains(".so"));
+            if previous.objects.contains_key(path) || is_library {
+                warnings.push(Finding::new(
+                    "REMOVED_OBJECT",
+                    format!("{} disappeared since {}", path.display(), previous.ident),
+                ));
+            } else {
+                removed_files.push(path.display().to_string());
+            }
+        }
+        if percent_of(removed_files.len(), previous.files.len())
+            .is_some_and(|share| share > thresholds.files_removed)
+        {
+            warnings.push(Finding::new(
+                "REMOVED_FILES",
+                format!(
+                    "{} files were removed since {}: {}",
+                    removed_files.len(),
+                    previous.ident,
+                    listed(removed_files.iter())
+                ),
+            ));
+        }
+        let added_files = self.files.difference(&previous.files).collect::<Vec<_>>();
+        if percent_of(added_files.len(), previous.files.len())
+            .is_some_and(|share| share > thresholds.files_added)
+        {
+            warnings.push(Finding::new(
+                "ADDED_FILES",
+                format!(
+                    "{} files were added since {}: {}",
+                    added_files.len(),
+                    previous.ident,
+                    listed(added_files.iter().map(|path| path.display()))
+                ),
+            ));
+        }
+
+        for (path, object) in self.objects.iter() {
+            let Some(previous_object) = previous.objects.get(path) else {
+                continue;
+            };
+            let added_needed = object
+                .needed
+                .difference(&previous_object.needed)
+                .collect::<Vec<_>>();
+            let removed_needed = previous_object
+                .needed
+                .difference(&object.needed)
+                .collect::<Vec<_>>();
+            if !added_needed.is_empty() {
+                warnings.push(Finding::new(
+                    "NEEDED",
+                    format!(
+                        "{} needs additional libraries since {}: {}",
+                        path.display(),
+                        previous.ident,
+                        listed(added_needed.iter())
+                    ),
+                ));
+            }
+            if !removed_needed.is_empty() {
+                warnings.push(Finding::new(
+                    "NEEDED",
+                    format!(
+                        "{} no longer needs libraries it needed in {}: {}",
+                        path.display(),
+                        previous.ident,
+                        listed(removed_needed.iter())
+                    ),
+                ));
+            }
+            let removed_exports = previous_object
+                .exports
+                .difference(&object.exports)
+                .collect::<Vec<_>>();
+            if !removed_exports.is_empty() {
+                warnings.push(Finding::new(
+                    "EXPORTS",
+                    format!(
+                        "{} no longer exports {} symbols it exported in {}: {}",
+                        path.display(),
+                        removed_exports.len(),
+                        previous.ident,
+                        listed(removed_exports.iter())
+                    ),
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Finds the latest artifact in the artifact cache that was released before the given artifact
+pub async fn previous_release(ident: &PackageArtifactIdent) -> Result<Option<PackageArtifact>> {
+    let cache_index = cache_index(&ident.origin, &ident.name).await?;
+    let previous = cache_index
+        .get(&ident.origin)
+        .and_then(|names| names.get(&ident.name))
+        .into_iter()
+        .flat_map(|versions| versions.iter())
+        .flat_map(|(version, targets)| {
+            targets
+                .get(&ident.target)
+                .into_iter()
+                .flat_map(move |releases| releases.iter().map(move |release| (release, version)))
+        })
+        .filter(|(release, _)| release.as_str() < ident.release.as_str())
+        .max();
+    let Some((release, version)) = previous else {
+        return Ok(None);
+    };
+    let previous = PackageArtifactIdent {
+        origin: ident.origin.clone(),
+        name: ident.name.clone(),
+        version: version.clone(),
+        release: release.clone(),
+        target: ident.target,
+    };
+    let artifact_path =
+        ValidFilePath::new(HAB_CACHE_ARTIFACTS_PATH.join(previous.to_string())).await?;
+    Ok(Some(PackageArtifact::new(&artifact_path).await?))
+}
+
+/// Parses the libraries an ELF object needs and the symbols it exports,
+/// returns `None` if the data is not an ELF object
+fn elf_summary(data: &[u8]) -> Option<ElfSummary> {
+    let elf = goblin::elf::Elf::parse(data).ok()?;
+    let exports = elf
+        .dynsyms
+        .iter()
+        .filter(|sym| {
+            sym.st_shndx != SHN_UNDEF as usize
+                && matches!(sym.st_bind(), STB_GLOBAL | STB_WEAK)
+                && matches!(
+                    sym.st_type(),
+                    STT_FUNC | STT_GNU_IFUNC | STT_OBJECT | STT_TLS
+                )
+                && sym.st_visibility() == STV_DEFAULT
+        })
+        .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name))
+        .map(String::from)
+        .collect();
+    Some(ElfSummary {
+        needed: elf.libraries.iter().map(|name| name.to_string()).collect(),
+        exports,
+    })
+}
+
+/// Relative change from `previous` to `current`, `None` if there was nothing before
+fn percent_change(previous: u64, current: u64) -> Option<f64> {
+    if previous == 0 {
+        return None;
+    }
+    Some((current as f64 - previous as f64) * 100.0 / previous as f64)
+}
+
+/// Share of `count` in `total` in percent, `None` if there was nothing before
+fn percent_of(count: usize, total: usize) -> Option<f64> {
+    if total == 0 {
+        return None;
+    }
+    Some(count as f64 * 100.0 / total as f64)
+}
+
+/// Lists the first few items, followed by the number of items left out
+fn listed(items: impl ExactSizeIterator<Item = impl ToString>) -> String {
+    let count = items.len();
+    let mut listed = items
+        .take(MAX_LISTED_ITEMS)
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if count > MAX_LISTED_ITEMS {
+        listed.push_str(&format!(" and {} more", count - MAX_LISTED_ITEMS));
+    }
+    listed
+}
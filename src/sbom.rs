@@ -0,0 +1,605 @@
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use reqwest::Url;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    path::Path,
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    checker::{LicenseCheck, PackageManifest},
+    is_metafile_name,
+    spdx::LicenseExpression,
+    PackageArtifact, PackageIdent, PackageTarget, ValidFilePath, FS_ROOT, HAB_CACHE_ARTIFACTS_PATH,
+    HAB_PKGS_PATH,
+};
+
+const NOASSERTION: &str = "NOASSERTION";
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SbomFormat {
+    /// SPDX 2.3 JSON document
+    Spdx,
+    /// CycloneDX 1.5 JSON document
+    Cyclonedx,
+}
+
+impl SbomFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            SbomFormat::Spdx => "sbom.spdx.json",
+            SbomFormat::Cyclonedx => "sbom.cdx.json",
+        }
+    }
+}
+
+/// A package in the runtime closure of an artifact
+struct SbomPackage {
+    ident: PackageIdent,
+    source: Option<Url>,
+    shasum: Option<String>,
+    declared_licenses: Option<String>,
+    detected_licenses: Option<BTreeSet<String>>,
+    deps: Vec<PackageIdent>,
+}
+
+/// Software bill of materials for an artifact and its runtime closure
+pub struct Sbom {
+    artifact_shasum: String,
+    packages: Vec<SbomPackage>,
+}
+
+impl Sbom {
+    /// Collects the metadata of the artifact and all its runtime dependencies, the
+    /// dependencies are read from the artifact cache or the installed packages.
+    /// Package sources are scanned for licenses if a license check is provided.
+    pub async fn new(
+        artifact: &PackageArtifact,
+        license_check: Option<&LicenseCheck>,
+    ) -> Result<Sbom> {
+        let root_ident = PackageIdent {
+            origin: artifact.ident.origin.clone(),
+            name: artifact.ident.name.clone(),
+            version: artifact.ident.version.clone(),
+            release: artifact.ident.release.clone(),
+        };
+        let mut packages = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(root_ident.clone());
+        queue.push_back((root_ident, Some(artifact.read_metafiles().await?)));
+        while let Some((ident, metafiles)) = queue.pop_front() {
+            let metafiles = match metafiles {
+                Some(metafiles) => metafiles,
+                None => match package_metafiles(&ident, artifact.ident.target).await {
+                    Ok(metafiles) => metafiles,
+                    Err(err) => {
+                        warn!(
+                            "Unable to read metadata of dependency {}, it will be listed without any details: {:#}",
+                            ident, err
+                        );
+                        HashMap::new()
+                    }
+                },
+            };
+            let manifest = PackageManifest::parse(
+                metafiles.get("MANIFEST").map(String::as_str).unwrap_or(""),
+            )?;
+            let deps = metafiles
+                .get("DEPS")
+                .map(|data| {
+                    data.lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(PackageIdent::try_from)
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()
+                .with_context(|| format!("Invalid DEPS metafile for package {}", ident))?
+                .unwrap_or_default();
+            for dep in deps.iter() {
+                if visited.insert(dep.clone()) {
+                    queue.push_back((dep.clone(), None));
+                }
+            }
+            let detected_licenses = match (license_check, &manifest.source, &manifest.shasum) {
+                (Some(license_check), Some(source), Some(shasum)) => {
+                    match license_check
+                        .check(FS_ROOT.as_path(), source.clone(), shasum)
+                        .await
+                    {
+                        Ok(scan) => scan.map(|scan| scan.detected()),
+                        Err(err) => {
+                            warn!(
+                                "Failed to scan sources of {} for licenses: {:#}",
+                                ident, err
+                            );
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            packages.push(SbomPackage {
+                ident,
+                source: manifest.source,
+                shasum: manifest.shasum,
+                declared_licenses: manifest.licenses.filter(|licenses| !licenses.is_empty()),
+                detected_licenses,
+                deps,
+            });
+        }
+        debug!(
+            "Collected {} packages for the bill of materials of {}",
+            packages.len(),
+            artifact.ident
+        );
+        Ok(Sbom {
+            artifact_shasum: file_shasum(&artifact.path).await?,
+            packages,
+        })
+    }
+
+    pub fn render(&self, format: SbomFormat) -> Result<String> {
+        match format {
+            SbomFormat::Spdx => serde_json::to_string_pretty(&self.spdx()),
+            SbomFormat::Cyclonedx => serde_json::to_string_pretty(&self.cyclonedx()),
+        }
+        .context("Failed to serialize software bill of materials")
+    }
+
+    fn spdx(&self) -> SpdxDocument {
+        let root = &self.packages[0];
+        let mut relationships = vec![SpdxRelationship {
+            spdx_element_id: String::from("SPDXRef-DOCUMENT"),
+            relationship_type: "DESCRIBES",
+            related_spdx_element: spdx_id(&root.ident),
+        }];
+        let mut packages = Vec::new();
+        for (index, package) in self.packages.iter().enumerate() {
+            let license_declared = package
+                .declared_licenses
+                .as_deref()
+                .and_then(spdx_expression)
+                .map(|expression| expression.to_string())
+                .unwrap_or_else(|| String::from(NOASSERTION));
+            // Only the checksum of the artifact being described is known, dependencies
+            // may have been read from the installed packages
+            let checksums = if index == 0 {
+                vec![SpdxChecksum {
+                    algorithm: "SHA256",
+                    checksum_value: self.artifact_shasum.clone(),
+                }]
+            } else {
+                Vec::new()
+            };
+            packages.push(SpdxPackage {
+                name: package.ident.name.clone(),
+                spdx_id: spdx_id(&package.ident),
+                version_info: format!("{}/{}", package.ident.version, package.ident.release),
+                supplier: format!("Organization: {}", package.ident.origin),
+                download_location: String::from(NOASSERTION),
+                files_analyzed: false,
+                checksums,
+                license_declared: license_declared.clone(),
+                license_concluded: String::from(NOASSERTION),
+                license_comments: spdx_license_comments(package),
+            });
+            // The source archive is described as its own package so that its checksum
+            // belongs to the location it is downloaded from
+            if let Some(source) = package.source.as_ref() {
+                packages.push(SpdxPackage {
+                    name: format!("{}-source", package.ident.name),
+                    spdx_id: spdx_source_id(&package.ident),
+                    version_info: package.ident.version.clone(),
+                    supplier: String::from(NOASSERTION),
+                    download_location: source.to_string(),
+                    files_analyzed: false,
+                    checksums: package
+                        .shasum
+                        .iter()
+                        .map(|shasum| SpdxChecksum {
+                            algorithm: "SHA256",
+                            checksum_value: shasum.clone(),
+                        })
+                        .collect(),
+                    license_declared,
+                    license_concluded: String::from(NOASSERTION),
+                    license_comments: None,
+                });
+                relationships.push(SpdxRelationship {
+                    spdx_element_id: spdx_id(&package.ident),
+                    relationship_type: "GENERATED_FROM",
+                    related_spdx_element: spdx_source_id(&package.ident),
+                });
+            }
+            for dep in package.deps.iter() {
+                relationships.push(SpdxRelationship {
+                    spdx_element_id: spdx_id(&package.ident),
+                    relationship_type: "DEPENDS_ON",
+                    related_spdx_element: spdx_id(dep),
+                });
+            }
+        }
+        SpdxDocument {
+            spdx_version: "SPDX-2.3",
+            data_license: "CC0-1.0",
+            spdx_id: "SPDXRef-DOCUMENT",
+            name: root.ident.to_string(),
+            document_namespace: format!(
+                "http://spdx.org/spdxdocs/{}-{}-{}-{}-{}",
+                root.ident.origin,
+                root.ident.name,
+                root.ident.version,
+                root.ident.release,
+                self.artifact_shasum
+            ),
+            creation_info: SpdxCreationInfo {
+                created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                creators: vec![format!(
+                    "Tool: hab-auto-build-{}",
+                    env!("CARGO_PKG_VERSION")
+                )],
+            },
+            packages,
+            relationships,
+        }
+    }
+
+    fn cyclonedx(&self) -> CycloneDxDocument {
+        let mut components = self
+            .packages
+            .iter()
+            .map(|package| {
+                let mut properties = vec![CycloneDxProperty {
+                    name: String::from("habitat:release"),
+                    value: package.ident.release.clone(),
+                }];
+                if let Some(licenses) = package.detected_licenses.as_ref() {
+                    for license in licenses.iter() {
+                        properties.push(CycloneDxProperty {
+                            name: String::from("hab-auto-build:detected-license"),
+                            value: license.clone(),
+                        });
+                    }
+                }
+                CycloneDxComponent {
+                    component_type: "library",
+                    bom_ref: package.ident.to_string(),
+                    group: package.ident.origin.clone(),
+                    name: package.ident.name.clone(),
+                    version: package.ident.version.clone(),
+                    licenses: package
+                        .declared_licenses
+                        .iter()
+                        .map(|licenses| match spdx_expression(licenses) {
+                            Some(expression) => CycloneDxLicense::Expression {
+                                expression: expression.to_string(),
+                            },
+                            None => CycloneDxLicense::License {
+                                license: CycloneDxNamedLicense {
+                                    name: licenses.clone(),
+                                },
+                            },
+                        })
+                        .collect(),
+                    external_references: package
+                        .source
+                        .iter()
+                        .map(|source| CycloneDxExternalReference {
+                            reference_type: "distribution",
+                            url: source.to_string(),
+                            hashes: package
+                                .shasum
+                                .iter()
+                                .map(|shasum| CycloneDxHash {
+                                    alg: "SHA-256",
+                                    content: shasum.clone(),
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                    properties,
+                }
+            })
+            .collect::<Vec<_>>();
+        let root = components.remove(0);
+        let shasum = &self.artifact_shasum;
+        CycloneDxDocument {
+            bom_format: "CycloneDX",
+            spec_version: "1.5",
+            // The serial number is derived from the artifact so that it is stable across runs
+            serial_number: format!(
+                "urn:uuid:{}",
+                Uuid::new_v5(
+                    &Uuid::NAMESPACE_URL,
+                    format!("urn:sha256:{}", shasum).as_bytes()
+                )
+            ),
+            version: 1,
+            metadata: CycloneDxMetadata {
+                timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                tools: vec![CycloneDxTool {
+                    name: "hab-auto-build",
+                    version: env!("CARGO_PKG_VERSION"),
+                }],
+                component: root,
+            },
+            components,
+            dependencies: self
+                .packages
+                .iter()
+                .map(|package| CycloneDxDependency {
+                    reference: package.ident.to_string(),
+                    depends_on: package.deps.iter().map(|dep| dep.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Reads the metafiles of a package from its artifact in the artifact cache,
+/// falling back to the installed package.
+async fn package_metafiles(
+    ident: &PackageIdent,
+    target: PackageTarget,
+) -> Result<HashMap<String, String>> {
+    let artifact_path = HAB_CACHE_ARTIFACTS_PATH.join(ident.artifact(target).to_string());
+    if let Ok(artifact_path) = ValidFilePath::new(artifact_path.as_path()).await {
+        return PackageArtifact::new(&artifact_path)
+            .await?
+            .read_metafiles()
+            .await;
+    }
+    let install_dir = HAB_PKGS_PATH
+        .join(&ident.origin)
+        .join(&ident.name)
+        .join(&ident.version)
+        .join(&ident.release);
+    let mut metafiles = HashMap::new();
+    let mut entries = tokio::fs::read_dir(install_dir.as_path())
+        .await
+        .with_context(|| {
+            format!(
+                "Package is neither in the artifact cache nor installed at {}",
+                install_dir.display()
+            )
+        })?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type().await?.is_file() && is_metafile_name(&name) {
+            metafiles.insert(name, tokio::fs::read_to_string(entry.path()).await?);
+        }
+    }
+    Ok(metafiles)
+}
+
+async fn file_shasum(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        let mut file = std::fs::File::open(path.as_path())?;
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>())
+    })
+    .await?
+}
+
+fn spdx_id(ident: &PackageIdent) -> String {
+    spdx_element_id("Package", ident)
+}
+
+fn spdx_source_id(ident: &PackageIdent) -> String {
+    spdx_element_id("Source", ident)
+}
+
+fn spdx_element_id(kind: &str, ident: &PackageIdent) -> String {
+    format!(
+        "SPDXRef-{}-{}",
+        kind,
+        ident
+            .to_string()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>()
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    version_info: String,
+    supplier: String,
+    download_location: String,
+    files_analyzed: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<SpdxChecksum>,
+    license_declared: String,
+    license_concluded: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license_comments: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxChecksum {
+    algorithm: &'static str,
+    checksum_value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDocument {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    serial_number: String,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: Vec<CycloneDxTool>,
+    component: CycloneDxComponent,
+}
+
+#[derive(Serialize)]
+struct CycloneDxTool {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    group: String,
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CycloneDxLicense>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    external_references: Vec<CycloneDxExternalReference>,
+    properties: Vec<CycloneDxProperty>,
+}
+
+/// A license expression, or a license name when the declared licenses are not an SPDX expression
+#[derive(Serialize)]
+#[serde(untagged)]
+enum CycloneDxLicense {
+    Expression { expression: String },
+    License { license: CycloneDxNamedLicense },
+}
+
+#[derive(Serialize)]
+struct CycloneDxNamedLicense {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct CycloneDxExternalReference {
+    #[serde(rename = "type")]
+    reference_type: &'static str,
+    url: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CycloneDxHash>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxHash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct CycloneDxProperty {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+/// Parses the declared licenses of a package, only expressions made of identifiers on the
+/// SPDX license list or license references are valid in an SBOM.
+fn spdx_expression(licenses: &str) -> Option<LicenseExpression> {
+    match LicenseExpression::parse(licenses) {
+        Ok(expression) if expression.unlisted_ids().is_empty() => Some(expression),
+        Ok(expression) => {
+            debug!(
+                "Declared licenses '{}' use identifiers not on the SPDX license list: {:?}",
+                licenses,
+                expression.unlisted_ids()
+            );
+            None
+        }
+        Err(err) => {
+            debug!(
+                "Declared licenses '{}' are not an SPDX license expression: {:#}",
+                licenses, err
+            );
+            None
+        }
+    }
+}
+
+/// Keeps the declared licenses that cannot be expressed in SPDX and the licenses
+/// detected in the package source as comments
+fn spdx_license_comments(package: &SbomPackage) -> Option<String> {
+    let mut comments = Vec::new();
+    if let Some(licenses) = package.declared_licenses.as_deref() {
+        if spdx_expression(licenses).is_none() {
+            comments.push(format!(
+                "Declared licenses are not a valid SPDX license expression: {}",
+                licenses
+            ));
+        }
+    }
+    if let Some(licenses) = package.detected_licenses.as_ref() {
+        comments.push(format!(
+            "Licenses detected in the package source: {}",
+            licenses.iter().cloned().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if comments.is_empty() {
+        None
+    } else {
+        Some(comments.join(". "))
+    }
+}
@@ -0,0 +1,393 @@
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Display,
+};
+
+use crate::checker::license_list_ids;
+
+lazy_static! {
+    static ref LICENSE_LIST_IDS: HashSet<String> = license_list_ids();
+}
+
+/// A parsed SPDX license expression as described in annex D of the SPDX specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseExpression {
+    License { id: String, or_later: bool },
+    With(Box<LicenseExpression>, String),
+    And(Box<LicenseExpression>, Box<LicenseExpression>),
+    Or(Box<LicenseExpression>, Box<LicenseExpression>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Id(String),
+    And,
+    Or,
+    With,
+    Open,
+    Close,
+}
+
+impl LicenseExpression {
+    /// Parses a license expression, license terms that are not joined by an
+    /// operator are treated as if they were joined with `AND`. This is how
+    /// habitat joins the elements of `pkg_license` in the MANIFEST.
+    pub fn parse(expression: &str) -> Result<LicenseExpression> {
+        let tokens = tokenize(expression)?;
+        let mut position = 0;
+        let mut result = parse_or(&tokens, &mut position)?;
+        while position < tokens.len() {
+            let next = parse_or(&tokens, &mut position)?;
+            result = LicenseExpression::And(Box::new(result), Box::new(next));
+        }
+        Ok(result)
+    }
+
+    /// All license identifiers in the expression, excluding exceptions
+    pub fn licenses(&self) -> BTreeSet<String> {
+        let mut licenses = BTreeSet::new();
+        self.visit(&mut |expression| {
+            if let LicenseExpression::License { id, .. } = expression {
+                licenses.insert(id.clone());
+            }
+        });
+        licenses
+    }
+
+    /// All exception identifiers in the expression
+    pub fn exceptions(&self) -> BTreeSet<String> {
+        let mut exceptions = BTreeSet::new();
+        self.visit(&mut |expression| {
+            if let LicenseExpression::With(_, exception) = expression {
+                exceptions.insert(exception.clone());
+            }
+        });
+        exceptions
+    }
+
+    /// The terms of the expression that must all hold, ie. the operands of the top level `AND`s
+    pub fn conjuncts(&self) -> Vec<&LicenseExpression> {
+        match self {
+            LicenseExpression::And(left, right) => {
+                let mut conjuncts = left.conjuncts();
+                conjuncts.extend(right.conjuncts());
+                conjuncts
+            }
+            expression => vec![expression],
+        }
+    }
+
+    /// License and exception identifiers in the expression that are neither on the
+    /// SPDX license list nor references to licenses defined elsewhere
+    pub fn unlisted_ids(&self) -> BTreeSet<String> {
+        self.licenses()
+            .into_iter()
+            .chain(self.exceptions())
+            .filter(|id| !is_license_ref(id) && !LICENSE_LIST_IDS.contains(id))
+            .collect()
+    }
+
+    /// Checks if the expression refers to the given license
+    pub fn mentions(&self, license: &str) -> bool {
+        self.licenses()
+            .iter()
+            .any(|id| license_id_base(id) == license_id_base(license))
+    }
+
+    /// Checks if the expression holds given a set of licenses found in the package source
+    pub fn is_satisfied_by(&self, licenses: &BTreeSet<String>) -> bool {
+        match self {
+            LicenseExpression::License { id, .. } => licenses
+                .iter()
+                .any(|license| license_id_base(license) == license_id_base(id)),
+            LicenseExpression::With(license, _) => license.is_satisfied_by(licenses),
+            LicenseExpression::And(left, right) => {
+                left.is_satisfied_by(licenses) && right.is_satisfied_by(licenses)
+            }
+            LicenseExpression::Or(left, right) => {
+                left.is_satisfied_by(licenses) || right.is_satisfied_by(licenses)
+            }
+        }
+    }
+
+    fn visit(&self, visitor: &mut impl FnMut(&LicenseExpression)) {
+        visitor(self);
+        match self {
+            LicenseExpression::License { .. } => {}
+            LicenseExpression::With(license, _) => license.visit(visitor),
+            LicenseExpression::And(left, right) | LicenseExpression::Or(left, right) => {
+                left.visit(visitor);
+                right.visit(visitor);
+            }
+        }
+    }
+}
+
+impl Display for LicenseExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseExpression::License { id, or_later } => {
+                write!(f, "{}{}", id, if *or_later { "+" } else { "" })
+            }
+            LicenseExpression::With(license, exception) => {
+                write!(f, "{} WITH {}", license, exception)
+            }
+            LicenseExpression::And(left, right) => {
+                for (index, operand) in [left, right].into_iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    if matches!(**operand, LicenseExpression::Or(_, _)) {
+                        write!(f, "({})", operand)?;
+                    } else {
+                        write!(f, "{}", operand)?;
+                    }
+                }
+                Ok(())
+            }
+            LicenseExpression::Or(left, right) => write!(f, "{} OR {}", left, right),
+        }
+    }
+}
+
+/// Returns true if the identifier refers to a license defined outside the SPDX license list
+pub fn is_license_ref(id: &str) -> bool {
+    id.starts_with("LicenseRef-") || id.starts_with("DocumentRef-")
+}
+
+/// Strips the `-only` and `-or-later` suffixes, askalono cannot tell these apart
+/// as the license texts are identical.
+fn license_id_base(id: &str) -> &str {
+    let id = id.trim_end_matches('+');
+    id.strip_suffix("-only")
+        .or_else(|| id.strip_suffix("-or-later"))
+        .unwrap_or(id)
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    for word in expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+    {
+        let token = match word {
+            "(" => Token::Open,
+            ")" => Token::Close,
+            "AND" => Token::And,
+            "OR" => Token::Or,
+            "WITH" => Token::With,
+            // Operators are case-sensitive, a lower case operator would otherwise be
+            // taken for a license identifier
+            word if ["AND", "OR", "WITH"]
+                .iter()
+                .any(|operator| word.eq_ignore_ascii_case(operator)) =>
+            {
+                return Err(anyhow!(
+                    "Operator '{}' must be written in upper case as '{}'",
+                    word,
+                    word.to_ascii_uppercase()
+                ));
+            }
+            word => {
+                let id = word.trim_end_matches('+');
+                if id.is_empty()
+                    || !id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == ':')
+                {
+                    return Err(anyhow!("Invalid license identifier '{}'", word));
+                }
+                Token::Id(word.to_string())
+            }
+        };
+        tokens.push(token);
+    }
+    if tokens.is_empty() {
+        return Err(anyhow!("Empty license expression"));
+    }
+    Ok(tokens)
+}
+
+fn parse_or(tokens: &[Token], position: &mut usize) -> Result<LicenseExpression> {
+    let mut expression = parse_and(tokens, position)?;
+    while tokens.get(*position) == Some(&Token::Or) {
+        *position += 1;
+        let right = parse_and(tokens, position)?;
+        expression = LicenseExpression::Or(Box::new(expression), Box::new(right));
+    }
+    Ok(expression)
+}
+
+fn parse_and(tokens: &[Token], position: &mut usize) -> Result<LicenseExpression> {
+    let mut expression = parse_with(tokens, position)?;
+    while tokens.get(*position) == Some(&Token::And) {
+        *position += 1;
+        let right = parse_with(tokens, position)?;
+        expression = LicenseExpression::And(Box::new(expression), Box::new(right));
+    }
+    Ok(expression)
+}
+
+fn parse_with(tokens: &[Token], position: &mut usize) -> Result<LicenseExpression> {
+    let expression = parse_term(tokens, position)?;
+    if tokens.get(*position) == Some(&Token::With) {
+        *position += 1;
+        match tokens.get(*position) {
+            Some(Token::Id(exception))
+                if matches!(expression, LicenseExpression::License { .. }) =>
+            {
+                *position += 1;
+                return Ok(LicenseExpression::With(
+                    Box::new(expression),
+                    exception.clone(),
+                ));
+            }
+            _ => return Err(anyhow!("Expected a license exception after 'WITH'")),
+        }
+    }
+    Ok(expression)
+}
+
+fn parse_term(tokens: &[Token], position: &mut usize) -> Result<LicenseExpression> {
+    match tokens.get(*position) {
+        Some(Token::Id(id)) => {
+            *position += 1;
+            Ok(LicenseExpression::License {
+                id: id.trim_end_matches('+').to_string(),
+                or_later: id.ends_with('+'),
+            })
+        }
+        Some(Token::Open) => {
+            *position += 1;
+            let expression = parse_or(tokens, position)?;
+            if tokens.get(*position) != Some(&Token::Close) {
+                return Err(anyhow!("Missing closing parenthesis"));
+            }
+            *position += 1;
+            Ok(expression)
+        }
+        Some(token) => Err(anyhow!("Unexpected {:?} in license expression", token)),
+        None => Err(anyhow!("Unexpected end of license expression")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(id: &str) -> LicenseExpression {
+        LicenseExpression::License {
+            id: id.to_string(),
+            or_later: false,
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            LicenseExpression::parse("MIT OR Apache-2.0 AND BSD-3-Clause").unwrap(),
+            LicenseExpression::Or(
+                Box::new(license("MIT")),
+                Box::new(LicenseExpression::And(
+                    Box::new(license("Apache-2.0")),
+                    Box::new(license("BSD-3-Clause"))
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        let expression = LicenseExpression::parse("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap();
+        assert_eq!(
+            expression,
+            LicenseExpression::And(
+                Box::new(LicenseExpression::Or(
+                    Box::new(license("MIT")),
+                    Box::new(license("Apache-2.0"))
+                )),
+                Box::new(license("BSD-3-Clause"))
+            )
+        );
+        assert_eq!(
+            expression.to_string(),
+            "(MIT OR Apache-2.0) AND BSD-3-Clause"
+        );
+    }
+
+    #[test]
+    fn with_applies_to_the_preceding_license() {
+        let expression =
+            LicenseExpression::parse("GPL-2.0-only WITH Classpath-exception-2.0 OR MIT").unwrap();
+        assert_eq!(
+            expression,
+            LicenseExpression::Or(
+                Box::new(LicenseExpression::With(
+                    Box::new(license("GPL-2.0-only")),
+                    "Classpath-exception-2.0".to_string()
+                )),
+                Box::new(license("MIT"))
+            )
+        );
+        assert_eq!(
+            expression.exceptions(),
+            BTreeSet::from(["Classpath-exception-2.0".to_string()])
+        );
+        assert_eq!(
+            expression.licenses(),
+            BTreeSet::from(["GPL-2.0-only".to_string(), "MIT".to_string()])
+        );
+    }
+
+    #[test]
+    fn plus_means_or_later() {
+        let expression = LicenseExpression::parse("LGPL-2.1+").unwrap();
+        assert_eq!(
+            expression,
+            LicenseExpression::License {
+                id: "LGPL-2.1".to_string(),
+                or_later: true
+            }
+        );
+        assert_eq!(expression.to_string(), "LGPL-2.1+");
+    }
+
+    #[test]
+    fn terms_without_operator_are_joined_with_and() {
+        assert_eq!(
+            LicenseExpression::parse("MIT Apache-2.0").unwrap(),
+            LicenseExpression::And(Box::new(license("MIT")), Box::new(license("Apache-2.0")))
+        );
+    }
+
+    #[test]
+    fn lower_case_operators_are_rejected() {
+        assert!(LicenseExpression::parse("MIT or Apache-2.0").is_err());
+        assert!(LicenseExpression::parse("MIT and Apache-2.0").is_err());
+        assert!(LicenseExpression::parse("GPL-2.0-only with Classpath-exception-2.0").is_err());
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for expression in [
+            "",
+            "MIT OR",
+            "AND MIT",
+            "(MIT OR Apache-2.0",
+            "MIT)",
+            "MIT WITH",
+            "(MIT OR Apache-2.0) WITH Classpath-exception-2.0",
+            "MIT/X11",
+            "+",
+        ] {
+            assert!(
+                LicenseExpression::parse(expression).is_err(),
+                "'{}' should not parse",
+                expression
+            );
+        }
+    }
+}
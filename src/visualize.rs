@@ -0,0 +1,413 @@
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use petgraph::{stable_graph::NodeIndex, visit::EdgeRef};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    path::PathBuf,
+    process::Stdio,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    DependencyType, PackageBuild, PackageBuildIdent, PackageDependencyGraph, PackageStudioType,
+    PackageType,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+    Graphml,
+    Mermaid,
+    Svg,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum GraphClustering {
+    Origin,
+    Repo,
+}
+
+#[derive(Debug, Serialize)]
+struct GraphNodeData<'a> {
+    id: usize,
+    ident: &'a PackageBuildIdent,
+    package_type: PackageType,
+    studio_type: Option<PackageStudioType>,
+    repo: &'a PathBuf,
+    path: &'a PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+struct GraphEdgeData {
+    source: usize,
+    target: usize,
+    dependency_type: DependencyType,
+}
+
+#[derive(Debug, Serialize)]
+struct GraphData<'a> {
+    nodes: Vec<GraphNodeData<'a>>,
+    edges: Vec<GraphEdgeData>,
+}
+
+/// A view over the nodes of a dependency graph that can be rendered into
+/// the supported graph formats.
+pub struct GraphExport<'a> {
+    graph: &'a PackageDependencyGraph,
+    nodes: Vec<NodeIndex>,
+    clustering: Option<GraphClustering>,
+}
+
+impl<'a> GraphExport<'a> {
+    pub fn new(
+        graph: &'a PackageDependencyGraph,
+        nodes: &HashSet<NodeIndex>,
+        clustering: Option<GraphClustering>,
+    ) -> GraphExport<'a> {
+        let mut nodes = nodes.iter().copied().collect::<Vec<_>>();
+        nodes.sort();
+        GraphExport {
+            graph,
+            nodes,
+            clustering,
+        }
+    }
+
+    pub async fn render(&self, format: GraphFormat) -> Result<String> {
+        match format {
+            GraphFormat::Dot => Ok(self.to_dot()),
+            GraphFormat::Json => self.to_json(),
+            GraphFormat::Graphml => Ok(self.to_graphml()),
+            GraphFormat::Mermaid => Ok(self.to_mermaid()),
+            GraphFormat::Svg => self.to_svg().await,
+        }
+    }
+
+    fn edges(&self) -> Vec<(NodeIndex, NodeIndex, DependencyType)> {
+        let mut edges = Vec::new();
+        for node in self.nodes.iter() {
+            for edge in self.graph.edges(*node) {
+                if self.nodes.binary_search(&edge.target()).is_ok() {
+                    edges.push((edge.source(), edge.target(), *edge.weight()));
+                }
+            }
+        }
+        edges
+    }
+
+    fn clusters(&self) -> BTreeMap<String, Vec<NodeIndex>> {
+        let mut clusters: BTreeMap<String, Vec<NodeIndex>> = BTreeMap::new();
+        for node in self.nodes.iter() {
+            let package = &self.graph[*node];
+            let key = match self.clustering {
+                Some(GraphClustering::Origin) => package.plan.ident.origin.clone(),
+                Some(GraphClustering::Repo) => package.plan.repo.display().to_string(),
+                None => String::new(),
+            };
+            clusters.entry(key).or_default().push(*node);
+        }
+        clusters
+    }
+
+    fn node_label(package: &PackageBuild) -> String {
+        package.plan.ident.to_string()
+    }
+
+    fn node_fill_color(package: &PackageBuild) -> &'static str {
+        match package.studio_type {
+            Some(PackageStudioType::Native) => "khaki",
+            Some(PackageStudioType::Bootstrap) => "lightsalmon",
+            Some(PackageStudioType::Standard) => "lightskyblue",
+            None => "lightgrey",
+        }
+    }
+
+    fn node_border_color(package: &PackageBuild) -> &'static str {
+        match package.package_type {
+            PackageType::Native => "darkorange",
+            PackageType::Standard => "blue",
+        }
+    }
+
+    fn node_class(package: &PackageBuild) -> &'static str {
+        match package.studio_type {
+            Some(PackageStudioType::Native) => "native",
+            Some(PackageStudioType::Bootstrap) => "bootstrap",
+            Some(PackageStudioType::Standard) => "standard",
+            None => "unbuildable",
+        }
+    }
+
+    fn dot_edge_style(dependency_type: DependencyType) -> &'static str {
+        match dependency_type {
+            DependencyType::Runtime => "color=darkgreen, style=solid",
+            DependencyType::Build => "color=blue, style=dashed",
+            DependencyType::Studio => "color=grey, style=dotted",
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let mut output = String::new();
+        writeln!(output, "digraph {{").unwrap();
+        writeln!(output, "    rankdir=LR;").unwrap();
+        writeln!(output, "    node [shape=rectangle, style=filled];").unwrap();
+        for (index, (cluster, nodes)) in self.clusters().iter().enumerate() {
+            let indent = if self.clustering.is_some() {
+                writeln!(output, "    subgraph cluster_{} {{", index).unwrap();
+                writeln!(output, "        label=\"{}\";", dot_escape(cluster)).unwrap();
+                "        "
+            } else {
+                "    "
+            };
+            for node in nodes.iter() {
+                let package = &self.graph[*node];
+                writeln!(
+                    output,
+                    "{}{} [label=\"{}\", color={}, fillcolor={}];",
+                    indent,
+                    node.index(),
+                    dot_escape(&GraphExport::node_label(package)),
+                    GraphExport::node_border_color(package),
+                    GraphExport::node_fill_color(package),
+                )
+                .unwrap();
+            }
+            if self.clustering.is_some() {
+                writeln!(output, "    }}").unwrap();
+            }
+        }
+        for (source, target, dependency_type) in self.edges() {
+            writeln!(
+                output,
+                "    {} -> {} [{}];",
+                source.index(),
+                target.index(),
+                GraphExport::dot_edge_style(dependency_type)
+            )
+            .unwrap();
+        }
+        writeln!(output, "}}").unwrap();
+        output
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let data = GraphData {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| {
+                    let package = &self.graph[*node];
+                    GraphNodeData {
+                        id: node.index(),
+                        ident: &package.plan.ident,
+                        package_type: package.package_type.clone(),
+                        studio_type: package.studio_type.clone(),
+                        repo: &package.plan.repo,
+                        path: &package.plan.path,
+                    }
+                })
+                .collect(),
+            edges: self
+                .edges()
+                .into_iter()
+                .map(|(source, target, dependency_type)| GraphEdgeData {
+                    source: source.index(),
+                    target: target.index(),
+                    dependency_type,
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&data).context("Failed to serialize dependency graph")
+    }
+
+    fn to_graphml(&self) -> String {
+        let mut output = String::new();
+        writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            output,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )
+        .unwrap();
+        for (key, domain) in [
+            ("ident", "node"),
+            ("origin", "node"),
+            ("name", "node"),
+            ("version", "node"),
+            ("package_type", "node"),
+            ("studio_type", "node"),
+            ("repo", "node"),
+            ("cluster", "node"),
+            ("dependency_type", "edge"),
+        ] {
+            writeln!(
+                output,
+                r#"  <key id="{}" for="{}" attr.name="{}" attr.type="string"/>"#,
+                key, domain, key
+            )
+            .unwrap();
+        }
+        writeln!(
+            output,
+            r#"  <graph id="dependencies" edgedefault="directed">"#
+        )
+        .unwrap();
+        let clusters = self
+            .clusters()
+            .into_iter()
+            .flat_map(|(cluster, nodes)| nodes.into_iter().map(move |node| (node, cluster.clone())))
+            .collect::<HashMap<_, _>>();
+        for node in self.nodes.iter() {
+            let package = &self.graph[*node];
+            writeln!(output, r#"    <node id="n{}">"#, node.index()).unwrap();
+            let mut data = vec![
+                ("ident", GraphExport::node_label(package)),
+                ("origin", package.plan.ident.origin.clone()),
+                ("name", package.plan.ident.name.clone()),
+                ("version", package.plan.ident.version.clone()),
+                ("package_type", String::from(package.package_type.clone())),
+                (
+                    "studio_type",
+                    package
+                        .studio_type
+                        .clone()
+                        .map(String::from)
+                        .unwrap_or_default(),
+                ),
+                ("repo", package.plan.repo.display().to_string()),
+            ];
+            if self.clustering.is_some() {
+                data.push(("cluster", clusters[node].clone()));
+            }
+            for (key, value) in data {
+                writeln!(
+                    output,
+                    r#"      <data key="{}">{}</data>"#,
+                    key,
+                    xml_escape(&value)
+                )
+                .unwrap();
+            }
+            writeln!(output, "    </node>").unwrap();
+        }
+        for (index, (source, target, dependency_type)) in self.edges().into_iter().enumerate() {
+            writeln!(
+                output,
+                r#"    <edge id="e{}" source="n{}" target="n{}">"#,
+                index,
+                source.index(),
+                target.index()
+            )
+            .unwrap();
+            writeln!(
+                output,
+                r#"      <data key="dependency_type">{:?}</data>"#,
+                dependency_type
+            )
+            .unwrap();
+            writeln!(output, "    </edge>").unwrap();
+        }
+        writeln!(output, "  </graph>").unwrap();
+        writeln!(output, "</graphml>").unwrap();
+        output
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut output = String::new();
+        writeln!(output, "flowchart LR").unwrap();
+        writeln!(output, "    classDef native fill:khaki").unwrap();
+        writeln!(output, "    classDef bootstrap fill:lightsalmon").unwrap();
+        writeln!(output, "    classDef standard fill:lightskyblue").unwrap();
+        writeln!(output, "    classDef unbuildable fill:lightgrey").unwrap();
+        for (index, (cluster, nodes)) in self.clusters().iter().enumerate() {
+            let indent = if self.clustering.is_some() {
+                writeln!(
+                    output,
+                    "    subgraph cluster_{} [\"{}\"]",
+                    index,
+                    mermaid_escape(cluster)
+                )
+                .unwrap();
+                "        "
+            } else {
+                "    "
+            };
+            for node in nodes.iter() {
+                let package = &self.graph[*node];
+                writeln!(
+                    output,
+                    "{}n{}[\"{}\"]:::{}",
+                    indent,
+                    node.index(),
+                    mermaid_escape(&GraphExport::node_label(package)),
+                    GraphExport::node_class(package)
+                )
+                .unwrap();
+            }
+            if self.clustering.is_some() {
+                writeln!(output, "    end").unwrap();
+            }
+        }
+        for (source, target, dependency_type) in self.edges() {
+            let arrow = match dependency_type {
+                DependencyType::Runtime => "-->",
+                DependencyType::Build => "-.->",
+                DependencyType::Studio => "-.->|studio|",
+            };
+            writeln!(
+                output,
+                "    n{} {} n{}",
+                source.index(),
+                arrow,
+                target.index()
+            )
+            .unwrap();
+        }
+        output
+    }
+
+    async fn to_svg(&self) -> Result<String> {
+        let mut child = tokio::process::Command::new("dot")
+            .arg("-Tsvg")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to invoke graphviz 'dot' command, please ensure it is installed")?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("child did not have a handle to stdin");
+        stdin.write_all(self.to_dot().as_bytes()).await?;
+        drop(stdin);
+        let output = child.wait_with_output().await?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            Err(anyhow!(
+                "Failed to render dependency graph to svg, exit code: {:?}: {}",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr)
+            ))
+        }
+    }
+}
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(value: &str) -> String {
+    value.replace('"', "#quot;")
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}